@@ -0,0 +1,115 @@
+// 并发压测 /api/report-visitor 的吞吐量
+//
+// 先启动服务器，然后运行:
+//   cargo run --release --example report_visitor_bench -- [地址] [并发数] [请求总数]
+// 默认: http://127.0.0.1:1111 64 10000
+//
+// 每个并发任务通过 X-Forwarded-For 使用不同的客户端IP（本机默认为可信代理），
+// 并先访问一次首页生成访问记录，使上报请求走完整的更新路径。
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// 单个任务的结果：(成功数, 失败数, 每个请求的耗时)
+type WorkerResult = (usize, usize, Vec<Duration>);
+
+fn parse_arg<T: std::str::FromStr>(args: &[String], index: usize, default: T) -> T {
+    args.get(index).and_then(|value| value.parse().ok()).unwrap_or(default)
+}
+
+// 计算耗时的百分位
+fn percentile(sorted: &[Duration], p: f64) -> Duration {
+    if sorted.is_empty() {
+        return Duration::ZERO;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+async fn worker(client: reqwest::Client, base: String, id: usize, remaining: Arc<AtomicUsize>) -> WorkerResult {
+    let ip = format!("10.{}.{}.{}", (id >> 16) & 0xff, (id >> 8) & 0xff, (id & 0xff).max(1));
+    let payload = serde_json::json!({
+        "country_code": "CN",
+        "country_name": "China",
+        "city": "Bench",
+    });
+
+    // 生成访问记录
+    let _ = client.get(format!("{}/", base)).header("X-Forwarded-For", &ip).send().await;
+
+    let mut ok = 0;
+    let mut failed = 0;
+    let mut latencies = Vec::new();
+
+    // 从共享计数中领取请求，直到全部发送完毕
+    while remaining
+        .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |left| left.checked_sub(1))
+        .is_ok()
+    {
+        let started = Instant::now();
+        let result = client
+            .post(format!("{}/api/report-visitor", base))
+            .header("X-Forwarded-For", &ip)
+            .json(&payload)
+            .send()
+            .await;
+        latencies.push(started.elapsed());
+
+        match result {
+            Ok(response) if response.status().is_success() => {
+                let _ = response.bytes().await;
+                ok += 1;
+            }
+            _ => failed += 1,
+        }
+    }
+
+    (ok, failed, latencies)
+}
+
+#[tokio::main]
+async fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let base = args.first().cloned().unwrap_or_else(|| "http://127.0.0.1:1111".to_string());
+    let base = base.trim_end_matches('/').to_string();
+    let concurrency: usize = parse_arg(&args, 1, 64).max(1);
+    let total: usize = parse_arg(&args, 2, 10000);
+
+    println!("目标: {}/api/report-visitor，并发: {}，请求数: {}", base, concurrency, total);
+
+    // 不带User-Agent的请求会被当作爬虫，不生成访问记录
+    let client = reqwest::Client::builder()
+        .user_agent("Mozilla/5.0 (X11; Linux x86_64) report-visitor-bench")
+        .pool_max_idle_per_host(concurrency)
+        .build()
+        .expect("创建HTTP客户端失败");
+    let remaining = Arc::new(AtomicUsize::new(total));
+
+    let started = Instant::now();
+    let handles: Vec<_> = (0..concurrency)
+        .map(|id| tokio::spawn(worker(client.clone(), base.clone(), id + 1, remaining.clone())))
+        .collect();
+
+    let mut ok = 0;
+    let mut failed = 0;
+    let mut latencies = Vec::with_capacity(total);
+    for handle in handles {
+        let (worker_ok, worker_failed, worker_latencies) = handle.await.expect("压测任务异常退出");
+        ok += worker_ok;
+        failed += worker_failed;
+        latencies.extend(worker_latencies);
+    }
+    let elapsed = started.elapsed();
+
+    latencies.sort();
+    println!("耗时: {:.2}秒，成功: {}，失败: {}", elapsed.as_secs_f64(), ok, failed);
+    println!("吞吐量: {:.0} 请求/秒", ok as f64 / elapsed.as_secs_f64());
+    println!(
+        "延迟: p50 {:?}，p95 {:?}，p99 {:?}，最大 {:?}",
+        percentile(&latencies, 0.50),
+        percentile(&latencies, 0.95),
+        percentile(&latencies, 0.99),
+        latencies.last().copied().unwrap_or_default()
+    );
+}
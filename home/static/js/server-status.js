@@ -1,36 +1,8 @@
-// 服务器配置
-const servers = [
-    {
-        name: "Current Server",
-        url: "/api/status",
-        showLatency: true
-    },
-    {
-        name: "Hong Kong Server",
-        url: "https://hk.lycrex.com/api/status",
-        showLatency: true
-    },
-    {
-        name: "Los Angeles Server",
-        url: "https://us.lycrex.com/api/status",
-        showLatency: true
-    },
-    {
-        name: "Japan Server",
-        url: "https://jp.lycrex.com/api/status",
-        showLatency: true
-    },
-    {
-        name: "Git & Lfs Server",
-        url: "https://git.lycrex.com",
-        showLatency: true
-    }
-];
-
 // 全局配置
 const config = {
+    nodesUrl: '/api/nodes',      // 节点汇总状态接口(由服务端轮询各节点)
     enableLatency: false,        // 是否启用延迟显示
-    updateInterval: 1000 * 60 * 30,     // 状态更新间隔(毫秒)
+    updateInterval: 1000 * 60,   // 状态更新间隔(毫秒)
     requestTimeout: 1000 * 5,        // 请求超时时间(毫秒)
     latencyThresholds: {
         excellent: 50,           // 极佳延迟阈值(毫秒)
@@ -43,6 +15,13 @@ const config = {
         minInterval: 5000,       // 指示灯闪烁最小间隔(毫秒)
         maxInterval: 15000,      // 指示灯闪烁最大间隔
         duration: 200            // 闪烁动画持续时间
+    },
+    sparkline: {
+        enabled: true,           // 是否为当前服务器显示指标曲线
+        metric: 'cpu',           // 指标: cpu 或 memory
+        range: '24h',            // 时间范围
+        width: 60,               // 曲线宽度(像素)
+        height: 12               // 曲线高度(像素)
     }
 };
 
@@ -52,6 +31,7 @@ class ServerStatusManager {
         this.containerId = containerId;
         this.container = null;
         this.styleElement = null;
+        this.nodes = [];
     }
 
     // 初始化状态管理器
@@ -63,24 +43,62 @@ class ServerStatusManager {
         // 添加样式
         this.addStyles();
         
-        // 创建服务器状态元素
-        this.createServerElements();
-        
-        // 获取初始状态(不包含延迟)
-        await this.fetchInitialStatus();
+        // 获取初始状态并创建服务器状态元素
+        await this.updateAllServers();
         
         // 显示容器
         this.showContainer();
         
-        // 如果启用延迟，延迟加载延迟信息
-        if (config.enableLatency) {
-            setTimeout(() => this.fetchLatencyData(), 5000);
-        }
-        
         // 设置定期更新
         setInterval(() => this.updateAllServers(), config.updateInterval);
     }
     
+    // 更新指标曲线(仅当前服务器提供历史接口)
+    async updateSparklines() {
+        if (!config.sparkline.enabled) return;
+        
+        const index = this.nodes.findIndex(node => node.local);
+        if (index < 0) return;
+        
+        const statusElement = document.getElementById(`server-${index}`);
+        if (!statusElement) return;
+        
+        try {
+            const { metric, range } = config.sparkline;
+            const response = await fetch(`/api/status/history?metric=${metric}&range=${range}`);
+            const data = await response.json();
+            if (!data.success || data.points.length < 2) return;
+            
+            this.renderSparkline(statusElement, data.points, `${metric} ${range}`);
+        } catch (error) {
+            console.error('Failed to fetch metric history:', error);
+        }
+    }
+    
+    // 使用内联SVG绘制指标曲线
+    renderSparkline(statusElement, points, label) {
+        const { width, height } = config.sparkline;
+        const values = points.map(point => point.value);
+        const max = Math.max(...values, 1);
+        const step = width / (values.length - 1);
+        
+        const path = values
+            .map((value, i) => `${(i * step).toFixed(1)},${(height - (value / max) * height).toFixed(1)}`)
+            .join(' ');
+        
+        let sparkline = statusElement.querySelector('.server-sparkline');
+        if (!sparkline) {
+            sparkline = document.createElementNS('http://www.w3.org/2000/svg', 'svg');
+            sparkline.classList.add('server-sparkline');
+            statusElement.insertBefore(sparkline, statusElement.querySelector('.server-latency'));
+        }
+        
+        sparkline.setAttribute('width', width);
+        sparkline.setAttribute('height', height);
+        sparkline.innerHTML = `<title>${label}: ${values[values.length - 1]}%</title>` +
+            `<polyline points="${path}" fill="none" stroke="currentColor" stroke-width="1" />`;
+    }
+    
     // 初始化容器
     initContainer() {
         const container = document.getElementById(this.containerId);
@@ -118,60 +136,30 @@ class ServerStatusManager {
     }
     
     // 创建服务器状态元素
-    createServerElements() {
-        servers.forEach((server, index) => {
+    createServerElements(nodes) {
+        this.container.innerHTML = '';
+        nodes.forEach((node, index) => {
             const serverId = `server-${index}`;
             const serverElement = document.createElement('p');
             serverElement.id = serverId;
             serverElement.innerHTML = `
                 <span class="status-indicator"></span>
-                <span class="server-name">${server.name}</span>
+                <span class="server-name"></span>
                 <span class="server-latency latency-hidden"></span>
             `;
+            serverElement.querySelector('.server-name').textContent = node.name;
             this.container.appendChild(serverElement);
         });
     }
     
-    // 获取初始状态
-    async fetchInitialStatus() {
-        const promises = servers.map((server, index) => {
-            return this.fetchServerStatus(server, true)
-                .then(statusData => {
-                    this.updateUI(`server-${index}`, statusData, false);
-                });
-        });
-        
-        await Promise.all(promises);
-    }
-    
     // 显示容器
     showContainer() {
         this.container.style.opacity = '1';
     }
     
-    // 获取延迟数据
-    async fetchLatencyData() {
-        // 确保所有延迟元素隐藏
-        this.setLatencyVisibility(false);
-        
-        // 获取状态和延迟
-        const promises = servers.map((server, index) => {
-            const skipLatency = !this.shouldShowLatency(server);
-            return this.fetchServerStatus(server, skipLatency)
-                .then(statusData => {
-                    this.updateUI(`server-${index}`, statusData, this.shouldShowLatency(server));
-                });
-        });
-        
-        await Promise.all(promises);
-        
-        // 显示延迟信息
-        setTimeout(() => this.setLatencyVisibility(true), 50);
-    }
-    
     // 检查是否应该显示服务器延迟
-    shouldShowLatency(server) {
-        return config.enableLatency && server.showLatency !== false;
+    shouldShowLatency(node) {
+        return config.enableLatency && node.show_latency !== false;
     }
     
     // 设置延迟元素的可见性
@@ -194,106 +182,67 @@ class ServerStatusManager {
             this.setLatencyVisibility(false);
         }
         
-        const promises = servers.map((server, index) => {
-            const skipLatency = !this.shouldShowLatency(server);
-            return this.fetchServerStatus(server, skipLatency)
-                .then(statusData => {
-                    this.updateUI(`server-${index}`, statusData, this.shouldShowLatency(server));
-                });
+        const nodes = await this.fetchNodes();
+        if (!nodes) return;
+        
+        // 节点列表变化时重新创建元素
+        const names = nodes.map(node => node.name).join('\n');
+        if (names !== this.nodes.map(node => node.name).join('\n')) {
+            this.createServerElements(nodes);
+        }
+        this.nodes = nodes;
+        
+        nodes.forEach((node, index) => {
+            this.updateUI(`server-${index}`, this.toStatusData(node), this.shouldShowLatency(node));
         });
         
-        await Promise.all(promises);
+        this.updateSparklines();
         
         if (config.enableLatency) {
             setTimeout(() => this.setLatencyVisibility(true), 50);
         }
     }
     
-    // 获取服务器状态
-    async fetchServerStatus(server, skipLatency = false) {
+    // 从服务端获取所有节点的汇总状态
+    async fetchNodes() {
         try {
-            let startTime = null;
-            let latency = null;
-            
-            if (!skipLatency) {
-                startTime = performance.now();
-            }
-            
             const controller = new AbortController();
             const timeoutId = setTimeout(() => controller.abort(), config.requestTimeout);
             
-            const response = await fetch(server.url, {
+            const response = await fetch(config.nodesUrl, {
                 method: 'GET',
-                mode: 'cors',
-                credentials: 'omit',
                 signal: controller.signal,
                 headers: { 'Accept': 'application/json' }
             });
             
             clearTimeout(timeoutId);
             
-            if (!skipLatency && startTime) {
-                latency = Math.round(performance.now() - startTime);
-            }
-            
             if (!response.ok) {
                 throw new Error(`HTTP error: ${response.status}`);
             }
             
             const data = await response.json();
-            
-            if (server.url === "/api/status") {
-                return {
-                    name: data.server.name,
-                    status: data.server.status,
-                    message: data.server.message,
-                    latency,
-                    showLatency: server.showLatency
-                };
-            }
-            
-            return {
-                name: server.name,
-                status: data.server?.status || "unknown",
-                message: data.server?.message || "无法获取服务器状态",
-                latency,
-                showLatency: server.showLatency
-            };
+            return data.nodes;
         } catch (error) {
-            // 检查连接性
-            const connectivityResult = await this.checkConnectivity(server.url, skipLatency);
-            
-            return {
-                name: server.name,
-                status: connectivityResult.connected ? "maintenance" : "error",
-                message: connectivityResult.connected ? "服务器可连接，但状态API不可用" : "服务器无法连接",
-                latency: connectivityResult.latency,
-                showLatency: server.showLatency
-            };
+            console.error('Failed to fetch node status:', error);
+            return null;
         }
     }
     
-    // 检查服务器连接性
-    async checkConnectivity(url, skipLatency) {
-        try {
-            let startTime = skipLatency ? null : performance.now();
-            
-            const controller = new AbortController();
-            const timeoutId = setTimeout(() => controller.abort(), config.requestTimeout);
-            
-            await fetch(url, {
-                method: 'HEAD',
-                mode: 'no-cors',
-                signal: controller.signal
-            });
-            
-            clearTimeout(timeoutId);
-            
-            const latency = startTime ? Math.round(performance.now() - startTime) : null;
-            return { connected: true, latency };
-        } catch {
-            return { connected: false, latency: null };
+    // 将节点状态转换为UI使用的数据
+    toStatusData(node) {
+        let message = node.message;
+        if (!node.local && node.checks > 0) {
+            message += ` [${node.availability}%]`;
         }
+        
+        return {
+            name: node.name,
+            status: node.status,
+            message,
+            latency: node.latency_ms,
+            showLatency: node.show_latency
+        };
     }
     
     // 更新UI
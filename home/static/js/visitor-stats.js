@@ -5,22 +5,22 @@ let visitorData = {
     reported: false
 };
 
-// 只在页面加载时执行一次的上报访问函数
+// 最近一次获取的状态数据和延迟
+let statusCache = null;
+let lastPingLatency = 0;
+
+// 只在页面加载时执行一次，获取服务端记录的IP和访问次数
 async function reportVisitOnce() {
     try {
-        // 获取访问者IP和地理信息
-        const visitorInfo = await getVisitorInfo();
-        visitorData.ipAddress = visitorInfo.ipAddress;
-        
-        // 上报访问信息
-        const reportResponse = await reportVisit(visitorInfo);
+        // 访问次数和地理位置由服务端在加载首页时记录
+        const response = await fetch('/api/current-ip');
+        const currentIp = await response.json();
         
-        if (reportResponse.success) {
-            visitorData.visits = reportResponse.visits;
-            visitorData.reported = true;
-        }
+        visitorData.ipAddress = currentIp.ip;
+        visitorData.visits = currentIp.visits;
+        visitorData.reported = true;
     } catch (error) {
-        console.error('Failed to report visitor data:', error);
+        console.error('Failed to load visitor data:', error);
     }
 }
 
@@ -28,13 +28,55 @@ async function reportVisitOnce() {
 async function fetchVisitorStats() {
     try {
         // 测量ping延迟
-        const pingLatency = await measurePingLatency();
+        lastPingLatency = await measurePingLatency();
         
         const statusResponse = await fetch('/api/status');
-        const statusData = await statusResponse.json();
+        statusCache = await statusResponse.json();
+        
+        renderVisitorStats();
+    } catch (error) {
+        console.error('Failed to fetch visitor statistics:', error);
+    }
+}
+
+// 订阅服务端状态推送，成功时返回true
+function subscribeStatusStream() {
+    if (!window.EventSource) {
+        return false;
+    }
+    
+    const source = new EventSource('/api/status/stream');
+    
+    // 连接建立后服务端首先推送完整状态
+    source.addEventListener('status', event => {
+        statusCache = JSON.parse(event.data);
+        renderVisitorStats();
+    });
+    
+    source.addEventListener('config', event => {
+        if (!statusCache) return;
+        statusCache.server = JSON.parse(event.data);
+        renderVisitorStats();
+    });
+    
+    source.addEventListener('visitors', event => {
+        if (!statusCache) return;
+        statusCache.visitor_stats = JSON.parse(event.data);
+        renderVisitorStats();
+    });
+    
+    return true;
+}
+
+// 根据缓存的状态数据渲染访问统计
+function renderVisitorStats() {
+    try {
+        const statusData = statusCache;
+        const pingLatency = lastPingLatency;
         
         // Check if configuration and visitor stats exist
-        if (!statusData.visitor_stats || 
+        if (!statusData ||
+            !statusData.visitor_stats || 
             !statusData.server || 
             !statusData.server.show_visitor_stats || 
             !statusData.server.show_visitor_stats.enabled) {
@@ -89,7 +131,7 @@ async function fetchVisitorStats() {
         visitorStatsElement.innerHTML = statsHtml;
         visitorStatsElement.style.display = 'block';
     } catch (error) {
-        console.error('Failed to fetch visitor statistics:', error);
+        console.error('Failed to render visitor statistics:', error);
     }
 }
 
@@ -101,63 +143,15 @@ async function initializeVisitorStats() {
     // 然后获取并显示统计信息
     await fetchVisitorStats();
     
-    // 设置定时刷新统计信息（不再包含上报功能）
-    setInterval(fetchVisitorStats, 60000);
+    // 优先使用服务端推送，不支持时定时刷新统计信息（不再包含上报功能）
+    if (!subscribeStatusStream()) {
+        setInterval(fetchVisitorStats, 60000);
+    }
 }
 
 // 页面加载后执行初始化
 document.addEventListener('DOMContentLoaded', initializeVisitorStats);
 
-// get visitor IP address and geo information
-async function getVisitorInfo() {
-    try {
-        const response = await fetch('https://api.db-ip.com/v2/free/self');
-        const data = await response.json();
-        
-        // 返回完整的地理位置信息
-        return {
-            ipAddress: data.ipAddress,
-            continentCode: data.continentCode,
-            continentName: data.continentName,
-            countryCode: data.countryCode,
-            countryName: data.countryName,
-            stateProv: data.stateProv,
-            city: data.city
-        };
-    } catch (error) {
-        console.error('Failed to get visitor info:', error);
-        // 返回默认值，仅包含IP地址
-        const randomIP = `192.168.${Math.floor(Math.random() * 255)}.${Math.floor(Math.random() * 255)}`;
-        return { ipAddress: randomIP };
-    }
-}
-
-// report visit information with geo data
-async function reportVisit(visitorInfo) {
-    try {
-        const response = await fetch('/api/report-visitor', {
-            method: 'POST',
-            headers: {
-                'Content-Type': 'application/json',
-            },
-            body: JSON.stringify({
-                ip: visitorInfo.ipAddress,
-                continent_code: visitorInfo.continentCode,
-                continent_name: visitorInfo.continentName,
-                country_code: visitorInfo.countryCode,
-                country_name: visitorInfo.countryName,
-                state_prov: visitorInfo.stateProv,
-                city: visitorInfo.city
-            }),
-        });
-        
-        return await response.json();
-    } catch (error) {
-        console.error('report visit failed:', error);
-        return { success: false, message: 'report visit failed', visits: 0 };
-    }
-}
-
 // 测量ping延迟
 async function measurePingLatency() {
     try {
@@ -0,0 +1,36 @@
+-- 初始数据库结构：访问统计、备忘录、公共便利贴和频道设置
+
+CREATE TABLE IF NOT EXISTS visitor_stats (
+    id INTEGER PRIMARY KEY,
+    total_visits INTEGER NOT NULL DEFAULT 0
+);
+
+CREATE TABLE IF NOT EXISTS ip_visits (
+    ip TEXT PRIMARY KEY,
+    visit_count INTEGER NOT NULL DEFAULT 0,
+    continent_code TEXT,
+    continent_name TEXT,
+    country_code TEXT,
+    country_name TEXT,
+    state_prov TEXT,
+    city TEXT,
+    last_visit INTEGER
+);
+
+CREATE TABLE IF NOT EXISTS user_notes (
+    user_id TEXT PRIMARY KEY,
+    content TEXT NOT NULL,
+    last_updated INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS public_notes (
+    channel_id INTEGER PRIMARY KEY,
+    content TEXT NOT NULL,
+    last_updated INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS user_channel_settings (
+    user_id TEXT PRIMARY KEY,
+    channel_id INTEGER NOT NULL DEFAULT 0,
+    last_updated INTEGER NOT NULL
+);
@@ -0,0 +1,48 @@
+-- 系统指标历史、服务监控记录和事件公告
+
+-- 系统指标采样（按分钟/小时聚合）
+CREATE TABLE IF NOT EXISTS metric_samples (
+    resolution TEXT NOT NULL,
+    bucket INTEGER NOT NULL,
+    cpu_sum REAL NOT NULL DEFAULT 0,
+    memory_sum REAL NOT NULL DEFAULT 0,
+    samples INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (resolution, bucket)
+);
+
+-- 服务监控检查记录
+CREATE TABLE IF NOT EXISTS monitor_checks (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    monitor TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    success INTEGER NOT NULL,
+    response_time_ms INTEGER,
+    status_code INTEGER,
+    error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_monitor_checks_monitor_time ON monitor_checks (monitor, timestamp);
+
+-- 事件/维护公告
+CREATE TABLE IF NOT EXISTS incidents (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    title TEXT NOT NULL,
+    severity TEXT NOT NULL,
+    affected_services TEXT NOT NULL DEFAULT '',
+    status TEXT NOT NULL,
+    created_at INTEGER NOT NULL,
+    scheduled_start INTEGER,
+    scheduled_end INTEGER,
+    resolved_at INTEGER,
+    resolution TEXT
+);
+
+-- 事件进展记录
+CREATE TABLE IF NOT EXISTS incident_updates (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    incident_id INTEGER NOT NULL,
+    timestamp INTEGER NOT NULL,
+    status TEXT NOT NULL,
+    message TEXT NOT NULL
+);
@@ -0,0 +1,38 @@
+-- 访问时间序列、爬虫计数、页面和来源统计、隐私盐值
+
+-- 访问时间序列（按小时/天聚合，每个IP一行用于统计独立访客）
+CREATE TABLE IF NOT EXISTS visit_buckets (
+    granularity TEXT NOT NULL,
+    bucket INTEGER NOT NULL,
+    ip TEXT NOT NULL,
+    visits INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (granularity, bucket, ip)
+);
+
+-- 爬虫访问计数（按匹配的User-Agent关键字）
+CREATE TABLE IF NOT EXISTS bot_visits (
+    pattern TEXT PRIMARY KEY,
+    visits INTEGER NOT NULL DEFAULT 0,
+    last_visit INTEGER NOT NULL
+);
+
+-- 隐私模式的盐值（按轮换周期）
+CREATE TABLE IF NOT EXISTS privacy_salts (
+    period INTEGER PRIMARY KEY,
+    salt TEXT NOT NULL
+);
+
+-- 页面访问和来源统计（按天聚合）
+CREATE TABLE IF NOT EXISTS page_views (
+    bucket INTEGER NOT NULL,
+    path TEXT NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (bucket, path)
+);
+
+CREATE TABLE IF NOT EXISTS referrer_visits (
+    bucket INTEGER NOT NULL,
+    host TEXT NOT NULL,
+    visits INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (bucket, host)
+);
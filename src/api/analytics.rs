@@ -0,0 +1,199 @@
+use axum::http::{header, Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+use rimplog::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::api::metrics::parse_range;
+use crate::api::visitor::classify_bot;
+use crate::config::get_config;
+use crate::db;
+
+// 路径的最大记录长度
+const MAX_PATH_LENGTH: usize = 128;
+
+// 排行榜默认和最大条数
+const DEFAULT_LIMIT: usize = 10;
+const MAX_LIMIT: usize = 100;
+
+// 排行查询参数
+#[derive(Deserialize)]
+pub struct TopQuery {
+    pub range: Option<String>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct PageEntry {
+    path: String,
+    views: u64,
+}
+
+#[derive(Serialize)]
+pub struct ReferrerEntry {
+    host: String,
+    visits: u64,
+}
+
+#[derive(Serialize)]
+pub struct PagesResponse {
+    pub success: bool,
+    pub message: Option<String>,
+    pub range: String,
+    pub pages: Vec<PageEntry>,
+}
+
+#[derive(Serialize)]
+pub struct ReferrersResponse {
+    pub success: bool,
+    pub message: Option<String>,
+    pub range: String,
+    pub referrers: Vec<ReferrerEntry>,
+}
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 规范化路径：去掉结尾的斜杠并限制长度
+fn normalize_path(path: &str) -> String {
+    let path = path.trim_end_matches('/');
+    let path = if path.is_empty() { "/" } else { path };
+    path.chars().take(MAX_PATH_LENGTH).collect::<String>().to_lowercase()
+}
+
+// 提取来源域名，站内跳转返回None
+fn referrer_host(referer: &str, own_host: Option<&str>) -> Option<String> {
+    let url = reqwest::Url::parse(referer).ok()?;
+    if url.scheme() != "http" && url.scheme() != "https" {
+        return None;
+    }
+
+    let host = url.host_str()?.to_lowercase();
+    let host = host.strip_prefix("www.").unwrap_or(&host).to_string();
+
+    // Host头可能带端口
+    let own_host = own_host
+        .map(|own| own.split(':').next().unwrap_or(own).to_lowercase());
+    if own_host.is_some_and(|own| own.strip_prefix("www.").unwrap_or(&own) == host) {
+        return None;
+    }
+
+    Some(host)
+}
+
+// 页面访问统计中间件：只记录成功返回的HTML页面，不包含接口和静态文件
+pub async fn track_page_view(req: Request<axum::body::Body>, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+    // 接口路径包括 /api/ 和 /profile/api/
+    let tracked = req.method() == Method::GET
+        && !path.starts_with("/static/")
+        && !path.contains("/api/")
+        && classify_bot(req.headers().get(header::USER_AGENT).and_then(|value| value.to_str().ok())).is_none();
+
+    let referrer = if tracked {
+        let own_host = req.headers().get(header::HOST).and_then(|value| value.to_str().ok());
+        req.headers().get(header::REFERER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|referer| referrer_host(referer, own_host))
+    } else {
+        None
+    };
+
+    let response = next.run(req).await;
+
+    let is_html = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("text/html"));
+    if tracked && response.status().is_success() && is_html {
+        let path = normalize_path(&path);
+        let result = db::run(move || db::record_page_view(&path, referrer.as_deref(), now_secs())).await;
+        if let Err(e) = result {
+            info!("记录页面访问失败: {}", e);
+        }
+    }
+
+    response
+}
+
+// 清理超出保留期限的页面和来源统计
+pub async fn purge_page_views() {
+    let before = now_secs().saturating_sub(get_config().visitor_history.day_retention_days.saturating_mul(86400));
+    if let Err(e) = db::run(move || db::purge_page_views(before)).await {
+        info!("清理页面访问统计失败: {}", e);
+    }
+}
+
+// 解析查询的时间范围和条数
+fn parse_top_query(query: &TopQuery) -> (String, Option<u64>, usize) {
+    let range = query.range.clone().unwrap_or_else(|| "7d".to_string()).to_lowercase();
+    let since = parse_range(&range).map(|seconds| now_secs().saturating_sub(seconds));
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    (range, since, limit)
+}
+
+// 获取时间窗口内的热门页面
+pub async fn get_top_pages(query: &TopQuery) -> PagesResponse {
+    let (range, since, limit) = parse_top_query(query);
+    let mut response = PagesResponse {
+        success: false,
+        message: None,
+        range,
+        pages: Vec::new(),
+    };
+
+    let since = match since {
+        Some(since) => since,
+        None => {
+            response.message = Some("无效的时间范围，例如: 24h, 7d, 30d".to_string());
+            return response;
+        }
+    };
+
+    match db::run(move || db::get_top_pages(since, limit)).await {
+        Ok(pages) => {
+            response.success = true;
+            response.pages = pages.into_iter()
+                .map(|(path, views)| PageEntry { path, views })
+                .collect();
+        }
+        Err(e) => response.message = Some(format!("获取页面统计失败: {}", e)),
+    }
+
+    response
+}
+
+// 获取时间窗口内的主要来源
+pub async fn get_top_referrers(query: &TopQuery) -> ReferrersResponse {
+    let (range, since, limit) = parse_top_query(query);
+    let mut response = ReferrersResponse {
+        success: false,
+        message: None,
+        range,
+        referrers: Vec::new(),
+    };
+
+    let since = match since {
+        Some(since) => since,
+        None => {
+            response.message = Some("无效的时间范围，例如: 24h, 7d, 30d".to_string());
+            return response;
+        }
+    };
+
+    match db::run(move || db::get_top_referrers(since, limit)).await {
+        Ok(referrers) => {
+            response.success = true;
+            response.referrers = referrers.into_iter()
+                .map(|(host, visits)| ReferrerEntry { host, visits })
+                .collect();
+        }
+        Err(e) => response.message = Some(format!("获取来源统计失败: {}", e)),
+    }
+
+    response
+}
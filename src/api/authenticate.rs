@@ -4,7 +4,8 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use once_cell::sync::Lazy;
 use std::time::{Duration, SystemTime};
-use crate::config::{get_server_config, get_auth_config};
+use crate::api::client_ip::UNKNOWN_IP;
+use crate::config::get_auth_config;
 
 #[derive(Deserialize)]
 pub struct AuthenticateRequest {
@@ -59,10 +60,7 @@ fn generate_and_store_token(ip_address: &str) -> (String, String) {
     let now = SystemTime::now();
     
     // 获取过期时间（秒）
-    let token_expiration_seconds = match get_auth_config() {
-        Some(auth_config) => auth_config.token_expiration_seconds,
-        None => get_server_config().auth.token_expiration_seconds,
-    };
+    let token_expiration_seconds = get_auth_config().token_expiration_seconds;
     
     // 计算过期时间点
     let expires_at = chrono::DateTime::<chrono::Local>::from(now)
@@ -106,10 +104,7 @@ pub fn check_token(token: &str, client_ip: Option<&str>) -> Option<TokenStatusIn
     }
     
     // 获取过期时间（秒）
-    let token_expiration_seconds = match get_auth_config() {
-        Some(auth_config) => auth_config.token_expiration_seconds,
-        None => get_server_config().auth.token_expiration_seconds,
-    };
+    let token_expiration_seconds = get_auth_config().token_expiration_seconds;
     
     // 检查token是否在集合中
     if let Ok(mut tokens) = VALID_TOKENS.lock() {
@@ -132,8 +127,8 @@ pub fn check_token(token: &str, client_ip: Option<&str>) -> Option<TokenStatusIn
             // 检查token是否已过期
             match &token_data.status {
                 TokenStatus::Active => {
-                    // 如果提供了客户端IP，还需要验证IP是否匹配
-                    if let Some(ip) = client_ip {
+                    // 如果提供了客户端IP，还需要验证IP是否匹配，颁发时无法确定IP的令牌不绑定IP
+                    if let Some(ip) = client_ip.filter(|_| token_data.ip_address != UNKNOWN_IP) {
                         if token_data.ip_address != ip {
                             debug!("Token IP不匹配: 期望 {}, 实际 {}", token_data.ip_address, ip);
                             return Some(status_info);
@@ -183,10 +178,7 @@ fn update_tokens_status(tokens: &mut HashMap<String, TokenData>) {
     // 使用下划线前缀表示有意不使用的变量
     let _now = SystemTime::now();
     // 从配置中获取令牌过期时间，优先使用最新配置
-    let token_expiration_seconds = match get_auth_config() {
-        Some(auth_config) => auth_config.token_expiration_seconds,
-        None => get_server_config().auth.token_expiration_seconds,
-    };
+    let token_expiration_seconds = get_auth_config().token_expiration_seconds;
     
     for (_, data) in tokens.iter_mut() {
         if let TokenStatus::Active = data.status {
@@ -240,23 +232,11 @@ pub fn list_valid_tokens() -> Vec<(String, String, String, bool)> {
 
 pub async fn authenticate_password(password: &str, client_ip: Option<&str>) -> AuthenticateResponse {
     // 如果没有提供客户端IP，记录错误但继续处理
-    let ip = client_ip.unwrap_or("unknown");
+    let ip = client_ip.unwrap_or(UNKNOWN_IP);
     debug!("处理密码验证，IP: {}", ip);
     
     // 从配置中获取系统密码，优先使用最新配置
-    let system_password = match get_auth_config() {
-        Some(auth_config) => {
-            debug!("使用最新认证配置: 密码={}, 令牌过期时间={}秒", 
-                   auth_config.password, auth_config.token_expiration_seconds);
-            auth_config.password
-        },
-        None => {
-            debug!("无法获取最新认证配置，使用静态配置");
-            get_server_config().auth.password.clone()
-        },
-    };
-    
-    debug!("用户输入密码: {}, 系统密码: {}", password, system_password);
+    let system_password = get_auth_config().password;
     
     // 简单验证密码是否匹配
     if password == system_password {
@@ -275,7 +255,7 @@ pub async fn authenticate_password(password: &str, client_ip: Option<&str>) -> A
         }
     } else {
         // 验证失败，不返回令牌
-        info!("密码验证失败，IP: {}, 输入: {}, 期望: {}", ip, password, system_password);
+        info!("密码验证失败，IP: {}", ip);
         AuthenticateResponse {
             success: false,
             message: "密码错误".to_string(),
@@ -289,10 +269,7 @@ pub fn get_token_details() -> Vec<TokenDisplayInfo> {
     if let Ok(mut tokens) = VALID_TOKENS.lock() {
         update_tokens_status(&mut tokens);
         
-        let token_expiration_seconds = match get_auth_config() {
-            Some(auth_config) => auth_config.token_expiration_seconds,
-            None => get_server_config().auth.token_expiration_seconds,
-        };
+        let token_expiration_seconds = get_auth_config().token_expiration_seconds;
         
         tokens
             .iter()
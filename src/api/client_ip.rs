@@ -0,0 +1,248 @@
+use axum::extract::{connect_info::Connected, ConnectInfo, FromRequestParts};
+use axum::http::{request::Parts, HeaderMap};
+use axum::serve::IncomingStream;
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use rimplog::info;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::RwLock;
+use tokio::net::{TcpListener, UnixListener};
+use crate::proxy_protocol::ProxyProtocolListener;
+
+// 无法确定客户端IP时使用的标识
+pub const UNKNOWN_IP: &str = "unknown";
+
+// 受信任的反向代理网段
+static TRUSTED_PROXIES: Lazy<RwLock<Vec<IpNet>>> = Lazy::new(|| RwLock::new(Vec::new()));
+
+// 连接的对端地址，Unix套接字的对端没有IP地址
+#[derive(Debug, Clone)]
+pub enum PeerAddr {
+    Tcp(SocketAddr),
+    Unix,
+}
+
+impl Connected<IncomingStream<'_, TcpListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self::Tcp(*stream.remote_addr())
+    }
+}
+
+// 启用PROXY协议时为协议头中的客户端地址
+impl Connected<IncomingStream<'_, ProxyProtocolListener>> for PeerAddr {
+    fn connect_info(stream: IncomingStream<'_, ProxyProtocolListener>) -> Self {
+        Self::Tcp(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, UnixListener>> for PeerAddr {
+    fn connect_info(_: IncomingStream<'_, UnixListener>) -> Self {
+        Self::Unix
+    }
+}
+
+// 客户端IP提取器：只有连接来自受信任代理时才解析转发头
+pub struct ClientIp(pub String);
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let peer = parts.extensions.get::<ConnectInfo<PeerAddr>>().map(|info| &info.0);
+        Ok(ClientIp(resolve(peer, &parts.headers)))
+    }
+}
+
+// 更新受信任代理列表，支持CIDR和单个IP
+pub fn set_trusted_proxies(entries: &[String]) {
+    let mut networks = Vec::new();
+    for entry in entries {
+        let entry = entry.trim();
+        let network = entry.parse::<IpNet>()
+            .or_else(|_| entry.parse::<IpAddr>().map(IpNet::from));
+        match network {
+            Ok(network) => networks.push(network),
+            Err(_) => info!("忽略无效的受信任代理: {}", entry),
+        }
+    }
+
+    info!("受信任代理: {:?}", networks);
+    if let Ok(mut trusted) = TRUSTED_PROXIES.write() {
+        *trusted = networks;
+    }
+}
+
+// 判断连接地址是否属于当前配置的受信任代理
+pub fn is_trusted_proxy(ip: &IpAddr) -> bool {
+    TRUSTED_PROXIES.read().is_ok_and(|trusted| is_trusted(&trusted, ip))
+}
+
+// 判断地址是否属于受信任代理
+fn is_trusted(trusted: &[IpNet], ip: &IpAddr) -> bool {
+    trusted.iter().any(|network| network.contains(ip))
+}
+
+// 解析转发链中的单个节点，无法识别的节点（如unknown或混淆标识）返回None
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+
+    // [2001:db8::1]:4711 或 [2001:db8::1]
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split(']').next()?.parse().ok();
+    }
+
+    value.parse::<IpAddr>().ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
+
+// 解析 Forwarded 头中的 for= 列表（RFC 7239）
+fn forwarded_chain(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let mut chain = Vec::new();
+    for value in headers.get_all("forwarded") {
+        let value = value.to_str().ok()?;
+        for element in value.split(',') {
+            let node = element.split(';')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(key, _)| key.trim().eq_ignore_ascii_case("for"))
+                .map(|(_, node)| parse_node(node));
+            if let Some(node) = node {
+                chain.push(node);
+            }
+        }
+    }
+
+    if chain.is_empty() { None } else { Some(chain) }
+}
+
+// 解析 X-Forwarded-For 列表
+fn x_forwarded_for_chain(headers: &HeaderMap) -> Option<Vec<Option<IpAddr>>> {
+    let mut chain = Vec::new();
+    for value in headers.get_all("x-forwarded-for") {
+        let value = value.to_str().ok()?;
+        chain.extend(value.split(',').map(parse_node));
+    }
+
+    if chain.is_empty() { None } else { Some(chain) }
+}
+
+// 从转发头中获取客户端IP：从右向左遍历转发链，跳过受信任代理，返回第一个不受信任的地址
+fn forwarded_client(headers: &HeaderMap, trusted: &[IpNet]) -> Option<IpAddr> {
+    if let Some(chain) = forwarded_chain(headers).or_else(|| x_forwarded_for_chain(headers)) {
+        let mut client = None;
+        for node in chain.into_iter().rev() {
+            match node {
+                Some(ip) => {
+                    client = Some(ip);
+                    if !is_trusted(trusted, &ip) {
+                        break;
+                    }
+                }
+                // 无法解析的节点之前的地址不可信，使用最近的可信节点
+                None => break,
+            }
+        }
+        return client;
+    }
+
+    // 代理只设置了 X-Real-IP
+    headers.get("x-real-ip")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_node)
+}
+
+// 获取客户端IP：连接地址不受信任时直接使用连接地址，否则从转发头中获取
+// Unix套接字只有有权限的本机进程（通常是反向代理）能连接，视为受信任代理
+// 都无法确定时返回 UNKNOWN_IP
+pub fn resolve(peer: Option<&PeerAddr>, headers: &HeaderMap) -> String {
+    match TRUSTED_PROXIES.read() {
+        Ok(trusted) => resolve_with(peer, headers, &trusted),
+        Err(_) => resolve_with(peer, headers, &[]),
+    }
+}
+
+fn resolve_with(peer: Option<&PeerAddr>, headers: &HeaderMap, trusted: &[IpNet]) -> String {
+    match peer {
+        Some(PeerAddr::Tcp(addr)) if !is_trusted(trusted, &addr.ip()) => addr.ip().to_string(),
+        Some(PeerAddr::Tcp(addr)) => forwarded_client(headers, trusted).unwrap_or(addr.ip()).to_string(),
+        Some(PeerAddr::Unix) => forwarded_client(headers, trusted)
+            .map(|ip| ip.to_string())
+            .unwrap_or_else(|| UNKNOWN_IP.to_string()),
+        None => UNKNOWN_IP.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn trusted() -> Vec<IpNet> {
+        vec!["10.0.0.0/8".parse().unwrap(), "127.0.0.1/32".parse().unwrap()]
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    fn ip(value: &str) -> Option<IpAddr> {
+        Some(value.parse().unwrap())
+    }
+
+    #[test]
+    fn forwarded_client_skips_trusted_proxies() {
+        let trusted = trusted();
+
+        let xff = headers(&[("x-forwarded-for", "198.51.100.7, 203.0.113.9, 10.0.0.2")]);
+        assert_eq!(forwarded_client(&xff, &trusted), ip("203.0.113.9"));
+
+        // 多个头按顺序拼接
+        let xff = headers(&[("x-forwarded-for", "198.51.100.7"), ("x-forwarded-for", "10.0.0.2")]);
+        assert_eq!(forwarded_client(&xff, &trusted), ip("198.51.100.7"));
+
+        // 全部为受信任代理时使用最左边的地址
+        let xff = headers(&[("x-forwarded-for", "10.0.0.3, 10.0.0.2")]);
+        assert_eq!(forwarded_client(&xff, &trusted), ip("10.0.0.3"));
+
+        // 无法解析的节点之前的地址不可信
+        let xff = headers(&[("x-forwarded-for", "198.51.100.7, garbage, 10.0.0.2")]);
+        assert_eq!(forwarded_client(&xff, &trusted), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn forwarded_header_takes_precedence() {
+        let trusted = trusted();
+        let forwarded = headers(&[
+            ("forwarded", "for=192.0.2.60;proto=http, for=\"[2001:db8:cafe::17]:4711\""),
+            ("x-forwarded-for", "198.51.100.7"),
+        ]);
+        assert_eq!(forwarded_client(&forwarded, &trusted), ip("2001:db8:cafe::17"));
+
+        let forwarded = headers(&[("forwarded", "for=192.0.2.60, for=10.1.2.3:8080")]);
+        assert_eq!(forwarded_client(&forwarded, &trusted), ip("192.0.2.60"));
+
+        let real_ip = headers(&[("x-real-ip", "192.0.2.61")]);
+        assert_eq!(forwarded_client(&real_ip, &trusted), ip("192.0.2.61"));
+        assert_eq!(forwarded_client(&HeaderMap::new(), &trusted), None);
+    }
+
+    #[test]
+    fn resolves_client_ip_by_peer() {
+        let trusted = trusted();
+        let xff = headers(&[("x-forwarded-for", "203.0.113.9")]);
+        let tcp = |addr: &str| PeerAddr::Tcp(addr.parse().unwrap());
+
+        // 不受信任的连接忽略转发头
+        assert_eq!(resolve_with(Some(&tcp("198.51.100.1:5000")), &xff, &trusted), "198.51.100.1");
+        assert_eq!(resolve_with(Some(&tcp("127.0.0.1:5000")), &xff, &trusted), "203.0.113.9");
+        assert_eq!(resolve_with(Some(&tcp("127.0.0.1:5000")), &HeaderMap::new(), &trusted), "127.0.0.1");
+
+        assert_eq!(resolve_with(Some(&PeerAddr::Unix), &xff, &trusted), "203.0.113.9");
+        assert_eq!(resolve_with(Some(&PeerAddr::Unix), &HeaderMap::new(), &trusted), UNKNOWN_IP);
+        assert_eq!(resolve_with(None, &xff, &trusted), UNKNOWN_IP);
+    }
+}
@@ -2,6 +2,7 @@ use rimplog::debug;
 use serde::Deserialize;
 use crate::api::authenticate;
 use crate::api::commands;
+use crate::storage::SharedStorage;
 
 // 命令请求结构体
 #[derive(Deserialize)]
@@ -16,7 +17,8 @@ pub async fn process_command_with_auth(
     command: &str, 
     password: Option<&str>, 
     token: Option<&str>,
-    client_ip: Option<&str>
+    client_ip: Option<&str>,
+    storage: SharedStorage
 ) -> commands::CommandResponse {
     let command_text = command.trim().to_lowercase();
     
@@ -54,6 +56,7 @@ pub async fn process_command_with_auth(
     
     // 解析命令和参数
     let (command_name, args) = commands::parse_command(&command_text);
+    let (_, raw_args) = commands::parse_command(command);
     
     // 寻找匹配的命令
     if let Some(cmd) = commands::COMMANDS.get(&command_name) {
@@ -63,8 +66,10 @@ pub async fn process_command_with_auth(
         let ctx = commands::CommandContext {
             command_text,
             args,
+            raw_args,
             is_authenticated,
             client_ip: client_ip.map(String::from),
+            storage,
         };
         
         // 执行命令
@@ -0,0 +1,81 @@
+use async_trait::async_trait;
+use super::{Command, CommandContext, CommandResponse, unauthorized_response};
+use crate::api::incident::format_time;
+use crate::backup;
+use crate::db;
+
+const USAGE: &str = "用法:
+- backup - 列出已有的数据库备份
+- backup now - 立即备份数据库";
+
+pub struct BackupCommand {}
+
+impl BackupCommand {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // 构造响应
+    fn response(success: bool, message: String) -> CommandResponse {
+        CommandResponse {
+            success,
+            message,
+            action: None,
+            token_status: None,
+            request_password: None,
+        }
+    }
+
+    // 列出已有的备份
+    fn list() -> CommandResponse {
+        let backups = backup::list_backups();
+        if backups.is_empty() {
+            return Self::response(true, "暂无数据库备份".to_string());
+        }
+
+        let mut message = format!("数据库备份 (共{}份):\n", backups.len());
+        for backup in backups.iter().rev() {
+            let name = backup.path.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let modified = backup.modified
+                .and_then(|modified| modified.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|elapsed| format_time(elapsed.as_secs()))
+                .unwrap_or_else(|| "-".to_string());
+            message.push_str(&format!("- {} ({:.1} KB, {})\n", name, backup.size as f64 / 1024.0, modified));
+        }
+
+        Self::response(true, message)
+    }
+}
+
+#[async_trait]
+impl Command for BackupCommand {
+    fn name(&self) -> &'static str {
+        "backup"
+    }
+
+    fn description(&self) -> &'static str {
+        "数据库备份 (backup now 立即备份)"
+    }
+
+    fn needs_auth(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, ctx: CommandContext) -> CommandResponse {
+        // 检查是否已认证
+        if !ctx.is_authenticated {
+            return unauthorized_response();
+        }
+
+        match ctx.args.first().map(String::as_str) {
+            None | Some("list") | Some("ls") => db::run(Self::list).await,
+            Some("now") => match db::run(move || backup::create_backup(&*ctx.storage)).await {
+                Ok(path) => Self::response(true, format!("数据库已备份到: {}", path.display())),
+                Err(message) => Self::response(false, message),
+            },
+            _ => Self::response(false, USAGE.to_string()),
+        }
+    }
+}
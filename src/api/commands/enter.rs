@@ -74,7 +74,7 @@ impl Command for EnterCommand {
     
     async fn execute(&self, ctx: CommandContext) -> CommandResponse {
         // 先根据命令名和参数直接查找目标
-        let cmd_parts: Vec<&str> = ctx.command_text.trim().split_whitespace().collect();
+        let cmd_parts: Vec<&str> = ctx.command_text.split_whitespace().collect();
         let cmd_name = if !cmd_parts.is_empty() { cmd_parts[0].to_lowercase() } else { String::new() };
         
         // 处理目标跳转
@@ -0,0 +1,197 @@
+use async_trait::async_trait;
+use super::{Command, CommandContext, CommandResponse, unauthorized_response};
+use crate::api::incident::{self, format_time};
+use crate::db::{self, Incident};
+
+const USAGE: &str = "用法:
+- incident - 显示未解决的事件和维护计划
+- incident show <ID> - 显示事件时间线
+- incident create <minor|major|critical> <服务,服务|-> <标题>
+- incident maintenance <开始> <结束> <服务,服务|-> <标题>
+  时间格式: 2024-05-01T02:00
+- incident update <ID> <investigating|identified|monitoring> <进展>
+- incident resolve <ID> <解决说明>";
+
+pub struct IncidentCommand {}
+
+impl IncidentCommand {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // 构造响应
+    fn response(success: bool, message: String) -> CommandResponse {
+        CommandResponse {
+            success,
+            message,
+            action: None,
+            token_status: None,
+            request_password: None,
+        }
+    }
+
+    // 格式化事件摘要
+    fn format_incident(incident: &Incident) -> String {
+        let services = if incident.affected_services.is_empty() {
+            "-".to_string()
+        } else {
+            incident.affected_services.join(", ")
+        };
+
+        let window = match (incident.scheduled_start, incident.scheduled_end) {
+            (Some(start), Some(end)) => format!("\n   时间: {} - {}", format_time(start), format_time(end)),
+            _ => String::new(),
+        };
+
+        format!(
+            "#{} [{}] {}\n   状态: {}  影响: {}{}\n",
+            incident.id, incident.severity, incident.title, incident.status, services, window
+        )
+    }
+
+    // 解析事件ID
+    fn parse_id(value: Option<&String>) -> Option<i64> {
+        value.and_then(|id| id.trim_start_matches('#').parse::<i64>().ok())
+    }
+
+    // 拼接剩余参数作为文本
+    fn join_text(args: &[String], from: usize) -> Option<String> {
+        if args.len() > from {
+            Some(args[from..].join(" "))
+        } else {
+            None
+        }
+    }
+
+    // 列出未解决的事件和维护计划，由 dispatch 在阻塞线程池中调用
+    fn list() -> CommandResponse {
+        let summary = incident::get_incidents_summary();
+        if summary.active.is_empty() && summary.maintenance.is_empty() {
+            return Self::response(true, "当前没有未解决的事件或维护计划".to_string());
+        }
+
+        let mut message = String::new();
+        if !summary.active.is_empty() {
+            message.push_str("进行中的事件:\n");
+            for item in summary.active.iter() {
+                message.push_str(&Self::format_incident(item));
+            }
+        }
+        if !summary.maintenance.is_empty() {
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str("维护计划:\n");
+            for item in summary.maintenance.iter() {
+                message.push_str(&Self::format_incident(item));
+            }
+        }
+
+        Self::response(true, message)
+    }
+
+    // 显示事件时间线，由 dispatch 在阻塞线程池中调用
+    fn show(id: i64) -> CommandResponse {
+        match db::get_incident(id) {
+            Ok(Some(item)) => {
+                let mut message = Self::format_incident(&item);
+                message.push_str("\n时间线:\n");
+                for update in item.updates.iter() {
+                    message.push_str(&format!(
+                        "- {} [{}] {}\n",
+                        format_time(update.timestamp),
+                        update.status,
+                        update.message
+                    ));
+                }
+                Self::response(true, message)
+            }
+            Ok(None) => Self::response(false, format!("事件 #{} 不存在", id)),
+            Err(e) => Self::response(false, format!("获取事件失败: {}", e)),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for IncidentCommand {
+    fn name(&self) -> &'static str {
+        "incident"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["incidents"]
+    }
+
+    fn description(&self) -> &'static str {
+        "管理事件和维护公告 (需要认证)\n用法: incident [show|create|maintenance|update|resolve]"
+    }
+
+    fn needs_auth(&self) -> bool {
+        true
+    }
+
+    async fn execute(&self, ctx: CommandContext) -> CommandResponse {
+        // 检查是否已认证
+        if !ctx.is_authenticated {
+            return unauthorized_response();
+        }
+
+        db::run(move || Self::dispatch(&ctx)).await
+    }
+}
+
+impl IncidentCommand {
+    // 执行子命令，涉及数据库操作，在阻塞线程池中调用
+    fn dispatch(ctx: &CommandContext) -> CommandResponse {
+        // 标题和说明保留原始大小写
+        let args = &ctx.raw_args;
+        let subcommand = match ctx.args.first() {
+            Some(subcommand) => subcommand.as_str(),
+            None => return Self::list(),
+        };
+
+        let result = match subcommand {
+            "list" | "ls" => return Self::list(),
+            "show" => match Self::parse_id(args.get(1)) {
+                Some(id) => return Self::show(id),
+                None => Err(format!("无效的事件ID\n{}", USAGE)),
+            },
+            "create" | "new" => match (ctx.args.get(1), args.get(2), Self::join_text(args, 3)) {
+                (Some(severity), Some(services), Some(title)) => {
+                    incident::create_incident(severity, services, &title)
+                        .map(|id| format!("已创建事件 #{}", id))
+                }
+                _ => Err(USAGE.to_string()),
+            },
+            "maintenance" | "schedule" => match (args.get(1), args.get(2), args.get(3), Self::join_text(args, 4)) {
+                (Some(start), Some(end), Some(services), Some(title)) => {
+                    incident::schedule_maintenance(start, end, services, &title)
+                        .map(|id| format!("已创建维护计划 #{}", id))
+                }
+                _ => Err(USAGE.to_string()),
+            },
+            "update" => match (Self::parse_id(args.get(1)), ctx.args.get(2), Self::join_text(args, 3)) {
+                (Some(id), Some(status), Some(message)) => {
+                    incident::update_incident(id, status, &message)
+                        .map(|_| format!("已更新事件 #{}", id))
+                }
+                (None, _, _) => Err(format!("无效的事件ID\n{}", USAGE)),
+                _ => Err(USAGE.to_string()),
+            },
+            "resolve" | "close" => match (Self::parse_id(args.get(1)), Self::join_text(args, 2)) {
+                (Some(id), Some(resolution)) => {
+                    incident::resolve_incident(id, &resolution)
+                        .map(|_| format!("事件 #{} 已解决", id))
+                }
+                (None, _) => Err(format!("无效的事件ID\n{}", USAGE)),
+                _ => Err(USAGE.to_string()),
+            },
+            _ => Err(format!("未知的incident子命令\n{}", USAGE)),
+        };
+
+        match result {
+            Ok(message) => Self::response(true, message),
+            Err(message) => Self::response(false, message),
+        }
+    }
+}
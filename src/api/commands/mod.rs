@@ -3,6 +3,7 @@ use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use once_cell::sync::Lazy;
+use crate::storage::SharedStorage;
 
 mod help;
 mod password;
@@ -10,6 +11,9 @@ mod system;
 mod echo;
 mod enter;
 mod token;
+mod monitor;
+mod incident;
+mod backup;
 
 // 重新导出所有命令模块
 pub use help::HelpCommand;
@@ -18,6 +22,9 @@ pub use system::SystemCommand;
 pub use echo::EchoCommand;
 pub use enter::EnterCommand;
 pub use token::TokenCommand;
+pub use monitor::MonitorCommand;
+pub use incident::IncidentCommand;
+pub use backup::BackupCommand;
 
 // 命令操作结构体
 #[derive(Serialize, Clone)]
@@ -49,8 +56,10 @@ pub struct CommandResponse {
 pub struct CommandContext {
     pub command_text: String,
     pub args: Vec<String>,
+    pub raw_args: Vec<String>, // 保留原始大小写的参数
     pub is_authenticated: bool,
     pub client_ip: Option<String>,
+    pub storage: SharedStorage,
 }
 
 // 定义命令特性，所有命令都需要实现此特性
@@ -86,6 +95,9 @@ pub static COMMANDS: Lazy<Arc<HashMap<String, Arc<dyn Command>>>> = Lazy::new(||
     register_command(&mut commands, Arc::new(SystemCommand::new()));
     register_command(&mut commands, Arc::new(EchoCommand::new()));
     register_command(&mut commands, Arc::new(TokenCommand::new()));
+    register_command(&mut commands, Arc::new(MonitorCommand::new()));
+    register_command(&mut commands, Arc::new(IncidentCommand::new()));
+    register_command(&mut commands, Arc::new(BackupCommand::new()));
     
     // 注册enter命令并保留引用用于特殊别名
     let enter_cmd: Arc<dyn Command> = Arc::new(EnterCommand::new());
@@ -113,7 +125,7 @@ fn register_command(commands: &mut HashMap<String, Arc<dyn Command>>, command: A
 
 // 解析命令并返回命令名称和参数
 pub fn parse_command(command_text: &str) -> (String, Vec<String>) {
-    let parts: Vec<&str> = command_text.trim().split_whitespace().collect();
+    let parts: Vec<&str> = command_text.split_whitespace().collect();
     
     if parts.is_empty() {
         return (String::new(), Vec::new());
@@ -131,7 +143,7 @@ pub fn get_all_commands() -> Vec<Arc<dyn Command>> {
     let mut processed = HashMap::new();
     
     // 通过遍历COMMANDS来收集所有唯一的命令实例
-    COMMANDS.iter().filter_map(|(_, cmd)| {
+    COMMANDS.values().filter_map(|cmd| {
         let name = cmd.name();
         if !processed.contains_key(name) {
             processed.insert(name, true);
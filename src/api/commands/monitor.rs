@@ -0,0 +1,98 @@
+use async_trait::async_trait;
+use super::{Command, CommandContext, CommandResponse};
+use crate::api::monitor::{self, MonitorSummary};
+use crate::db;
+
+pub struct MonitorCommand {}
+
+impl MonitorCommand {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    // 格式化可用率
+    fn format_uptime(uptime: Option<f64>) -> String {
+        uptime.map(|value| format!("{}%", value)).unwrap_or_else(|| "-".to_string())
+    }
+
+    // 格式化单个服务的监控信息
+    fn format_summary(summary: &MonitorSummary) -> String {
+        let (marker, state) = match summary.up {
+            Some(true) => ("✅", "在线".to_string()),
+            Some(false) => ("❌", format!("不可用 ({})", summary.error.as_deref().unwrap_or("未知错误"))),
+            None => ("⏳", "等待检查".to_string()),
+        };
+
+        let response_time = summary.response_time_ms
+            .map(|ms| format!(" {}ms", ms))
+            .unwrap_or_default();
+
+        format!(
+            "{} {}: {}{}\n   可用率: 24h {} | 7d {} | 30d {}\n",
+            marker,
+            summary.name,
+            state,
+            response_time,
+            Self::format_uptime(summary.uptime.h24),
+            Self::format_uptime(summary.uptime.d7),
+            Self::format_uptime(summary.uptime.d30),
+        )
+    }
+}
+
+#[async_trait]
+impl Command for MonitorCommand {
+    fn name(&self) -> &'static str {
+        "monitor"
+    }
+
+    fn aliases(&self) -> Vec<&'static str> {
+        vec!["monitors", "uptime"]
+    }
+
+    fn description(&self) -> &'static str {
+        "查看服务可用性 (monitor [服务名])"
+    }
+
+    async fn execute(&self, ctx: CommandContext) -> CommandResponse {
+        // 每个服务都要查询数据库，在阻塞线程池中执行
+        let mut summaries = db::run(monitor::get_monitor_summaries).await;
+
+        // 指定了服务名时只显示该服务
+        if let Some(name) = ctx.args.first() {
+            summaries.retain(|summary| summary.name.eq_ignore_ascii_case(name));
+            if summaries.is_empty() {
+                return CommandResponse {
+                    success: false,
+                    message: format!("未找到服务: {}\n输入 monitor 查看所有服务", name),
+                    action: None,
+                    token_status: None,
+                    request_password: None,
+                };
+            }
+        }
+
+        if summaries.is_empty() {
+            return CommandResponse {
+                success: true,
+                message: "当前没有配置服务监控".to_string(),
+                action: None,
+                token_status: None,
+                request_password: None,
+            };
+        }
+
+        let mut message = "服务状态:\n".to_string();
+        for summary in summaries.iter() {
+            message.push_str(&Self::format_summary(summary));
+        }
+
+        CommandResponse {
+            success: true,
+            message,
+            action: None,
+            token_status: None,
+            request_password: None,
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use chrono::{Local, NaiveDateTime, TimeZone};
+use rimplog::info;
+use crate::api::status;
+use crate::db::{self, Incident, IncidentUpdate};
+
+// 事件严重程度
+pub const SEVERITIES: [&str; 3] = ["minor", "major", "critical"];
+
+// 事件处理中的状态
+pub const INCIDENT_STATUSES: [&str; 3] = ["investigating", "identified", "monitoring"];
+
+// 维护时间的输入格式
+pub const TIME_FORMAT: &str = "%Y-%m-%dT%H:%M";
+
+// 状态接口中的事件汇总
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct IncidentsSummary {
+    pub active: Vec<Incident>,
+    pub maintenance: Vec<Incident>,
+}
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 解析本地时间，例如 2024-05-01T02:00
+pub fn parse_time(value: &str) -> Option<u64> {
+    let naive = NaiveDateTime::parse_from_str(value, TIME_FORMAT).ok()?;
+    let timestamp = Local.from_local_datetime(&naive).single()?.timestamp();
+    u64::try_from(timestamp).ok()
+}
+
+// 格式化时间戳为本地时间
+pub fn format_time(timestamp: u64) -> String {
+    Local.timestamp_opt(timestamp as i64, 0)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| timestamp.to_string())
+}
+
+// 解析受影响的服务列表，"-" 表示不指定
+fn parse_services(services: &str) -> Vec<String> {
+    if services == "-" {
+        return Vec::new();
+    }
+
+    services
+        .split(',')
+        .map(|service| service.trim().to_lowercase())
+        .filter(|service| !service.is_empty())
+        .collect()
+}
+
+// 获取未解决的事件和未结束的维护
+pub fn get_incidents_summary() -> IncidentsSummary {
+    let now = now_secs();
+    let incidents = match db::get_open_incidents(now) {
+        Ok(incidents) => incidents,
+        Err(e) => {
+            info!("获取事件列表失败: {}", e);
+            return IncidentsSummary::default();
+        }
+    };
+
+    let mut summary = IncidentsSummary::default();
+    for mut incident in incidents {
+        if incident.kind == "maintenance" {
+            // 维护状态根据时间窗口计算
+            if incident.scheduled_start.is_some_and(|start| start <= now) {
+                incident.status = "in_progress".to_string();
+            }
+            summary.maintenance.push(incident);
+        } else {
+            summary.active.push(incident);
+        }
+    }
+
+    summary
+}
+
+// 推送事件变更
+fn publish_incidents() {
+    status::publish_event("incidents", serde_json::json!(get_incidents_summary()));
+}
+
+// 创建事件
+pub fn create_incident(severity: &str, services: &str, title: &str) -> Result<i64, String> {
+    if !SEVERITIES.contains(&severity) {
+        return Err(format!("无效的严重程度: {}，可用: {}", severity, SEVERITIES.join(", ")));
+    }
+
+    let now = now_secs();
+    let incident = Incident {
+        id: 0,
+        kind: "incident".to_string(),
+        title: title.to_string(),
+        severity: severity.to_string(),
+        affected_services: parse_services(services),
+        status: "investigating".to_string(),
+        created_at: now,
+        scheduled_start: None,
+        scheduled_end: None,
+        resolved_at: None,
+        resolution: None,
+        updates: vec![IncidentUpdate {
+            timestamp: now,
+            status: "investigating".to_string(),
+            message: title.to_string(),
+        }],
+    };
+
+    let id = db::create_incident(&incident).map_err(|e| format!("创建事件失败: {}", e))?;
+    info!("创建事件 #{}: [{}] {}", id, severity, title);
+    publish_incidents();
+    Ok(id)
+}
+
+// 计划维护
+pub fn schedule_maintenance(start: &str, end: &str, services: &str, title: &str) -> Result<i64, String> {
+    let scheduled_start = parse_time(start)
+        .ok_or_else(|| format!("无效的开始时间: {}，格式: 2024-05-01T02:00", start))?;
+    let scheduled_end = parse_time(end)
+        .ok_or_else(|| format!("无效的结束时间: {}，格式: 2024-05-01T04:00", end))?;
+    if scheduled_end <= scheduled_start {
+        return Err("结束时间必须晚于开始时间".to_string());
+    }
+
+    let now = now_secs();
+    let incident = Incident {
+        id: 0,
+        kind: "maintenance".to_string(),
+        title: title.to_string(),
+        severity: "maintenance".to_string(),
+        affected_services: parse_services(services),
+        status: "scheduled".to_string(),
+        created_at: now,
+        scheduled_start: Some(scheduled_start),
+        scheduled_end: Some(scheduled_end),
+        resolved_at: None,
+        resolution: None,
+        updates: vec![IncidentUpdate {
+            timestamp: now,
+            status: "scheduled".to_string(),
+            message: title.to_string(),
+        }],
+    };
+
+    let id = db::create_incident(&incident).map_err(|e| format!("创建维护计划失败: {}", e))?;
+    info!("计划维护 #{}: {} ({} - {})", id, title, start, end);
+    publish_incidents();
+    Ok(id)
+}
+
+// 添加事件进展
+pub fn update_incident(id: i64, status: &str, message: &str) -> Result<(), String> {
+    if !INCIDENT_STATUSES.contains(&status) {
+        return Err(format!("无效的状态: {}，可用: {}", status, INCIDENT_STATUSES.join(", ")));
+    }
+
+    let update = IncidentUpdate {
+        timestamp: now_secs(),
+        status: status.to_string(),
+        message: message.to_string(),
+    };
+
+    match db::add_incident_update(id, &update, false) {
+        Ok(true) => {
+            publish_incidents();
+            Ok(())
+        }
+        Ok(false) => Err(format!("事件 #{} 不存在或已解决", id)),
+        Err(e) => Err(format!("更新事件失败: {}", e)),
+    }
+}
+
+// 解决事件或结束维护
+pub fn resolve_incident(id: i64, resolution: &str) -> Result<(), String> {
+    let update = IncidentUpdate {
+        timestamp: now_secs(),
+        status: "resolved".to_string(),
+        message: resolution.to_string(),
+    };
+
+    match db::add_incident_update(id, &update, true) {
+        Ok(true) => {
+            info!("事件 #{} 已解决: {}", id, resolution);
+            publish_incidents();
+            Ok(())
+        }
+        Ok(false) => Err(format!("事件 #{} 不存在或已解决", id)),
+        Err(e) => Err(format!("解决事件失败: {}", e)),
+    }
+}
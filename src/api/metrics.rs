@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use sysinfo::System;
+use tokio::time::{interval, Duration};
+use rimplog::info;
+use crate::config::get_config;
+use crate::db;
+
+// 采样任务状态
+static SAMPLER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// 历史查询参数
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub metric: Option<String>,
+    pub range: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct HistoryPoint {
+    timestamp: u64,
+    value: f64,
+}
+
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    pub success: bool,
+    pub message: Option<String>,
+    pub metric: String,
+    pub range: String,
+    pub resolution: String,
+    pub points: Vec<HistoryPoint>,
+}
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 启动系统指标采样任务
+pub fn start_metrics_sampler() {
+    let metrics_config = get_config().metrics.clone();
+    if !metrics_config.enabled {
+        info!("系统指标采样已禁用");
+        return;
+    }
+
+    // 防止重复启动
+    if SAMPLER_RUNNING.swap(true, Ordering::SeqCst) {
+        info!("系统指标采样任务已在运行中");
+        return;
+    }
+
+    let interval_secs = metrics_config.sample_interval_seconds.max(1);
+    info!("启动系统指标采样，间隔：{}秒", interval_secs);
+
+    tokio::spawn(async move {
+        // 保持同一个System实例，CPU使用率需要两次刷新之间的差值
+        let mut sys = System::new();
+        sys.refresh_cpu_usage();
+
+        let mut interval_timer = interval(Duration::from_secs(interval_secs));
+        // 第一次tick立即返回，跳过以保证CPU使用率有意义
+        interval_timer.tick().await;
+
+        loop {
+            interval_timer.tick().await;
+
+            sys.refresh_cpu_usage();
+            sys.refresh_memory();
+
+            let cpu = sys.global_cpu_usage() as f64;
+            let memory = if sys.total_memory() > 0 {
+                sys.used_memory() as f64 / sys.total_memory() as f64 * 100.0
+            } else {
+                0.0
+            };
+
+            let now = now_secs();
+            // 清理超出保留期限的数据
+            let minute_before = now.saturating_sub(metrics_config.minute_retention_hours * 3600);
+            let hour_before = now.saturating_sub(metrics_config.hour_retention_days * 86400);
+            db::run(move || {
+                if let Err(e) = db::record_metric_sample(now, cpu, memory) {
+                    info!("记录系统指标失败: {}", e);
+                }
+                if let Err(e) = db::purge_metric_samples(minute_before, hour_before) {
+                    info!("清理系统指标失败: {}", e);
+                }
+            }).await;
+        }
+    });
+}
+
+// 解析时间范围，例如 30m、24h、7d，单位无效或数值溢出时返回None
+pub fn parse_range(range: &str) -> Option<u64> {
+    let range = range.trim();
+    let (split, unit) = range.char_indices().last()?;
+    let value: u64 = range[..split].parse().ok()?;
+    let multiplier = match unit {
+        'm' => 60,
+        'h' => 3600,
+        'd' => 86400,
+        _ => return None,
+    };
+
+    value.checked_mul(multiplier).filter(|seconds| *seconds > 0)
+}
+
+// 查询指标历史数据
+pub fn get_history(query: &HistoryQuery) -> HistoryResponse {
+    let metric = query.metric.clone().unwrap_or_else(|| "cpu".to_string()).to_lowercase();
+    let range = query.range.clone().unwrap_or_else(|| "24h".to_string()).to_lowercase();
+
+    let mut response = HistoryResponse {
+        success: false,
+        message: None,
+        metric: metric.clone(),
+        range: range.clone(),
+        resolution: String::new(),
+        points: Vec::new(),
+    };
+
+    if metric != "cpu" && metric != "memory" {
+        response.message = Some("未知的指标，可用: cpu, memory".to_string());
+        return response;
+    }
+
+    let seconds = match parse_range(&range) {
+        Some(seconds) => seconds,
+        None => {
+            response.message = Some("无效的时间范围，例如: 30m, 24h, 7d".to_string());
+            return response;
+        }
+    };
+
+    // 在分钟数据保留期内使用分钟精度，否则使用小时精度
+    let metrics_config = &get_config().metrics;
+    let resolution = if seconds <= metrics_config.minute_retention_hours * 3600 {
+        "minute"
+    } else {
+        "hour"
+    };
+    let since = now_secs().saturating_sub(seconds);
+
+    match db::get_metric_history(resolution, &metric, since) {
+        Ok(points) => {
+            response.success = true;
+            response.resolution = resolution.to_string();
+            response.points = points
+                .into_iter()
+                .map(|(timestamp, value)| HistoryPoint {
+                    timestamp,
+                    value: (value * 100.0).round() / 100.0,
+                })
+                .collect();
+        }
+        Err(e) => {
+            response.message = Some(format!("获取指标历史失败: {}", e));
+        }
+    }
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_range;
+
+    #[test]
+    fn parses_units() {
+        assert_eq!(parse_range("30m"), Some(1800));
+        assert_eq!(parse_range(" 24h "), Some(86400));
+        assert_eq!(parse_range("7d"), Some(604800));
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        assert_eq!(parse_range(""), None);
+        assert_eq!(parse_range("h"), None);
+        assert_eq!(parse_range("0h"), None);
+        assert_eq!(parse_range("10s"), None);
+        assert_eq!(parse_range("-1h"), None);
+    }
+
+    #[test]
+    fn rejects_non_ascii_units() {
+        assert_eq!(parse_range("24时"), None);
+        assert_eq!(parse_range("1é"), None);
+        assert_eq!(parse_range("时"), None);
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(parse_range(&format!("{}d", u64::MAX)), None);
+        assert_eq!(parse_range(&format!("{}m", u64::MAX / 60 + 1)), None);
+    }
+}
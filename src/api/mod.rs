@@ -2,26 +2,61 @@ use axum::{
     routing::{get, post},
     Router,
     Json,
-    extract::ConnectInfo,
-    http::HeaderMap,
-    response::IntoResponse,
+    extract::{DefaultBodyLimit, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    response::sse::{Event, KeepAlive, Sse},
 };
+use futures::stream::{self, Stream, StreamExt};
 use rimplog::debug;
 use serde_json::json;
-use std::net::SocketAddr;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use crate::config::get_config;
+use crate::storage::SharedStorage;
+use client_ip::ClientIp;
 
 pub mod status;
 pub mod visitor;
 pub mod command;
 pub mod authenticate;
 pub mod commands;
+pub mod metrics;
+pub mod monitor;
+pub mod incident;
+pub mod client_ip;
+pub mod privacy;
+pub mod analytics;
+pub mod transfer;
 
-pub fn api_routes() -> Router {
+// 导入访客数据的请求体上限
+const IMPORT_BODY_LIMIT: usize = 64 * 1024 * 1024;
+
+// 导出导入的格式参数
+#[derive(serde::Deserialize)]
+struct TransferQuery {
+    format: Option<String>,
+}
+
+pub fn api_routes() -> Router<SharedStorage> {
     Router::new()
         .route("/status", get(status_handler))
+        .route("/status/history", get(status_history_handler))
+        .route("/status/stream", get(status_stream_handler))
+        .route("/nodes", get(nodes_handler))
+        .route("/monitors", get(monitors_handler))
         .route("/visitor", get(visitor_handler))
+        .route("/visitor/history", get(visitor_history_handler))
+        .route("/visitor/pages", get(visitor_pages_handler))
+        .route("/visitor/referrers", get(visitor_referrers_handler))
+        .route("/visitor/geo", get(visitor_geo_handler))
+        .route("/visitor/export", get(visitor_export_handler))
+        .route("/visitor/import", post(visitor_import_handler).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)))
+        .route("/visitor/me", get(visitor::get_own_visit_record).delete(visitor::delete_own_visit_record))
         .route("/current-ip", get(current_ip_handler))
         .route("/version", get(version_handler))
+        .route("/config/reload-status", get(config_reload_status_handler))
         .route("/report-visitor", post(report_visitor_handler))
         .route("/command", post(command_handler))
         .route("/authenticate", post(authenticate_handler))
@@ -29,15 +64,65 @@ pub fn api_routes() -> Router {
         .route("/ping", get(ping_handler))
 }
 
-async fn status_handler() -> Json<serde_json::Value> {
-    let status = status::get_status().await;
+async fn status_handler(State(storage): State<SharedStorage>) -> Json<serde_json::Value> {
+    let status = status::get_status(storage).await;
     Json(json!(status))
 }
 
-async fn visitor_handler() -> Json<serde_json::Value> {
+// 通过SSE推送状态更新：首先发送完整状态，之后推送系统、配置和访问统计变更
+async fn status_stream_handler(
+    State(storage): State<SharedStorage>
+) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let receiver = status::subscribe_events();
+    let initial = status::get_status(storage).await;
+    let heartbeat = get_config().status_stream.heartbeat_seconds.max(1);
+    
+    let initial_event = Event::default().event("status").data(json!(initial).to_string());
+    let updates = stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse_event = Event::default().event(event.event).data(event.data.to_string());
+                    return Some((Ok(sse_event), receiver));
+                }
+                // 客户端处理过慢时跳过积压的事件
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    });
+    
+    Sse::new(stream::once(async move { Ok(initial_event) }).chain(updates))
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(heartbeat)).text("heartbeat"))
+}
+
+// 获取系统指标历史数据，用于绘制状态曲线
+async fn status_history_handler(Query(query): Query<metrics::HistoryQuery>) -> Json<serde_json::Value> {
+    let history = crate::db::run(move || metrics::get_history(&query)).await;
+    Json(json!(history))
+}
+
+// 获取当前服务器及对等节点的汇总状态
+async fn nodes_handler() -> Json<serde_json::Value> {
+    let nodes = status::get_nodes();
+    Json(json!({
+        "nodes": nodes
+    }))
+}
+
+// 获取服务监控状态和可用率
+async fn monitors_handler() -> Json<serde_json::Value> {
+    let monitors = crate::db::run(monitor::get_monitor_summaries).await;
+    Json(json!({
+        "monitors": monitors
+    }))
+}
+
+async fn visitor_handler(State(storage): State<SharedStorage>) -> Json<serde_json::Value> {
     // 使用数据库API获取访问统计
-    let total_visits = crate::db::get_total_visits().unwrap_or(0);
-    let unique_ips = crate::db::get_unique_ip_count().unwrap_or(0);
+    let (total_visits, unique_ips) = crate::db::run(move || {
+        (storage.total_visits().unwrap_or(0), storage.unique_visitors().unwrap_or(0))
+    }).await;
     
     Json(json!({
         "total_visits": total_visits,
@@ -45,11 +130,157 @@ async fn visitor_handler() -> Json<serde_json::Value> {
     }))
 }
 
-async fn current_ip_handler(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Json<serde_json::Value> {
-    let ip = addr.ip().to_string();
+// 获取按小时/天聚合的访问历史
+async fn visitor_history_handler(
+    State(storage): State<SharedStorage>,
+    Query(query): Query<visitor::VisitorHistoryQuery>
+) -> Json<serde_json::Value> {
+    let history = crate::db::run(move || visitor::get_visit_history(&*storage, &query)).await;
+    Json(json!(history))
+}
+
+// 获取访客地理分布
+async fn visitor_geo_handler(
+    State(storage): State<SharedStorage>,
+    Query(query): Query<visitor::GeoQuery>
+) -> Json<serde_json::Value> {
+    let geo = crate::db::run(move || visitor::get_geo_breakdown(&*storage, &query)).await;
+    Json(json!(geo))
+}
+
+// 校验请求中的Bearer令牌
+fn is_authorized(headers: &HeaderMap, client_ip: &str) -> bool {
+    extract_bearer_token(headers)
+        .is_some_and(|token| authenticate::validate_token(&token, Some(client_ip)))
+}
+
+// 未认证响应
+fn unauthorized() -> Response {
+    (StatusCode::UNAUTHORIZED, Json(json!({
+        "success": false,
+        "message": "未认证或令牌已过期"
+    }))).into_response()
+}
+
+// 获取当前配置的加载时间和最近一次重新加载失败的原因 (需要认证)
+async fn config_reload_status_handler(ClientIp(client_ip): ClientIp, headers: HeaderMap) -> Response {
+    if !is_authorized(&headers, &client_ip) {
+        return unauthorized();
+    }
+    
+    Json(json!({
+        "success": true,
+        "loaded_at": crate::config::config_loaded_at(),
+        "last_error": crate::config::last_reload_error()
+    })).into_response()
+}
+
+// 解析导出导入格式，默认JSON
+fn transfer_format(query: &TransferQuery) -> Option<transfer::TransferFormat> {
+    match query.format.as_deref() {
+        None => Some(transfer::TransferFormat::Json),
+        Some(format) => transfer::TransferFormat::parse(format),
+    }
+}
+
+// 格式错误响应
+fn invalid_format() -> Response {
+    (StatusCode::BAD_REQUEST, Json(json!({
+        "success": false,
+        "message": "未知的格式，可用: json, csv"
+    }))).into_response()
+}
+
+// 导出访客统计 (需要认证)
+async fn visitor_export_handler(
+    State(storage): State<SharedStorage>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    Query(query): Query<TransferQuery>
+) -> Response {
+    if !is_authorized(&headers, &client_ip) {
+        return unauthorized();
+    }
+    let format = match transfer_format(&query) {
+        Some(format) => format,
+        None => return invalid_format(),
+    };
+    
+    match crate::db::run(move || transfer::export_visitors(&*storage, format)).await {
+        Ok(body) => {
+            let disposition = format!("attachment; filename=\"visitors.{}\"", format.extension());
+            (
+                [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)],
+                body,
+            ).into_response()
+        }
+        Err(message) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({
+            "success": false,
+            "message": message
+        }))).into_response(),
+    }
+}
+
+// 导入并合并访客统计 (需要认证)
+async fn visitor_import_handler(
+    State(storage): State<SharedStorage>,
+    ClientIp(client_ip): ClientIp,
+    headers: HeaderMap,
+    Query(query): Query<TransferQuery>,
+    body: String
+) -> Response {
+    if !is_authorized(&headers, &client_ip) {
+        return unauthorized();
+    }
+    let format = match transfer_format(&query) {
+        Some(format) => format,
+        None => return invalid_format(),
+    };
+    
+    let result = crate::db::run(move || {
+        let result = transfer::import_visitors(&*storage, format, &body);
+        if result.is_ok() {
+            status::publish_visitor_stats(&*storage);
+        }
+        result
+    }).await;
     
-    // 使用数据库API获取IP访问次数
-    let visits = crate::db::get_ip_visit_count(&ip).unwrap_or(0);
+    match result {
+        Ok(summary) => {
+            Json(json!({
+                "success": true,
+                "message": format!("导入完成: 新增{}个IP，合并{}个IP", summary.inserted, summary.merged),
+                "summary": summary
+            })).into_response()
+        }
+        Err(message) => (StatusCode::BAD_REQUEST, Json(json!({
+            "success": false,
+            "message": message
+        }))).into_response(),
+    }
+}
+
+// 获取时间窗口内的热门页面
+async fn visitor_pages_handler(Query(query): Query<analytics::TopQuery>) -> Json<serde_json::Value> {
+    let pages = analytics::get_top_pages(&query).await;
+    Json(json!(pages))
+}
+
+// 获取时间窗口内的主要来源
+async fn visitor_referrers_handler(Query(query): Query<analytics::TopQuery>) -> Json<serde_json::Value> {
+    let referrers = analytics::get_top_referrers(&query).await;
+    Json(json!(referrers))
+}
+
+async fn current_ip_handler(
+    State(storage): State<SharedStorage>,
+    ClientIp(ip): ClientIp
+) -> Json<serde_json::Value> {
+    // 使用数据库API获取IP访问次数，按隐私设置转换后查询
+    let key_ip = ip.clone();
+    let visits = crate::db::run(move || {
+        storage.visitor_visits(&privacy::visitor_key(&key_ip)).unwrap_or(0)
+    }).await;
     
     Json(json!({
         "ip": ip,
@@ -57,12 +288,17 @@ async fn current_ip_handler(ConnectInfo(addr): ConnectInfo<SocketAddr>) -> Json<
     }))
 }
 
-// 处理客户端上报的访问者IP
-async fn report_visitor_handler(payload: Json<visitor::VisitorReportRequest>) -> Json<serde_json::Value> {
-    let response = visitor::report_visitor_ip(payload).await;
+// 处理客户端上报的访问信息，只能补充调用者自己的记录
+async fn report_visitor_handler(
+    State(storage): State<SharedStorage>,
+    ClientIp(ip): ClientIp,
+    payload: Json<visitor::VisitorReportRequest>
+) -> Json<serde_json::Value> {
+    let response = visitor::report_visitor_ip(storage, ip, payload).await;
     Json(json!({
         "success": response.success,
         "message": response.message,
+        "ip": response.ip,
         "visits": response.visits
     }))
 }
@@ -98,54 +334,21 @@ async fn version_handler() -> Json<serde_json::Value> {
     }))
 }
 
-// 从请求中提取客户端IP地址
-fn extract_client_ip(conn_info: Option<&ConnectInfo<SocketAddr>>, headers: &HeaderMap) -> Option<String> {
-    // 优先从连接信息获取
-    if let Some(ConnectInfo(addr)) = conn_info {
-        return Some(addr.ip().to_string());
-    }
-    
-    // 尝试从X-Forwarded-For头获取
-    if let Some(forwarded) = headers.get("x-forwarded-for") {
-        if let Ok(forwarded_str) = forwarded.to_str() {
-            if let Some(ip) = forwarded_str.split(',').next() {
-                return Some(ip.trim().to_string());
-            }
-        }
-    }
-    
-    // 尝试从X-Real-IP头获取
-    if let Some(real_ip) = headers.get("x-real-ip") {
-        if let Ok(ip) = real_ip.to_str() {
-            return Some(ip.trim().to_string());
-        }
-    }
-    
-    None
-}
-
 // 从请求头中提取Bearer令牌
 fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
     headers.get("authorization").and_then(|value| {
         let value_str = value.to_str().ok()?;
-        if value_str.starts_with("Bearer ") {
-            Some(value_str[7..].to_string())
-        } else {
-            None
-        }
+        value_str.strip_prefix("Bearer ").map(|token| token.to_string())
     })
 }
 
 // 处理命令输入
 async fn command_handler(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>, 
+    State(storage): State<SharedStorage>,
+    ClientIp(client_ip): ClientIp,
     headers: HeaderMap, 
     Json(payload): Json<command::CommandRequest>
 ) -> Json<serde_json::Value> {
-    // 获取客户端IP
-    let client_ip = extract_client_ip(Some(&ConnectInfo(addr)), &headers);
-    let ip_str = client_ip.as_deref().unwrap_or("unknown");
-    
     // 从Authorization头提取token
     let header_token = extract_bearer_token(&headers);
     
@@ -155,7 +358,7 @@ async fn command_handler(
     // 输出调试信息
     debug!("收到命令请求: command={}, 来自IP={}, 有Authorization头: {}, 有效token: {}", 
             payload.command,
-            ip_str,
+            client_ip,
             header_token.is_some(),
             token.is_some()
     );
@@ -166,7 +369,7 @@ async fn command_handler(
     // 检查token并获取状态信息
     let is_valid_token = if let Some(t) = token {
         // 获取详细token状态
-        if let Some(status_info) = authenticate::check_token(t, client_ip.as_deref()) {
+        if let Some(status_info) = authenticate::check_token(t, Some(client_ip.as_str())) {
             // 构建token状态结构
             let valid = status_info.is_valid;
             token_status = Some(commands::TokenStatus {
@@ -206,7 +409,8 @@ async fn command_handler(
         &payload.command, 
         password,
         valid_token,
-        client_ip.as_deref()
+        Some(client_ip.as_str()),
+        storage
     ).await;
     
     // 添加token状态到响应
@@ -217,17 +421,12 @@ async fn command_handler(
 
 // 处理密码验证
 async fn authenticate_handler(
-    ConnectInfo(addr): ConnectInfo<SocketAddr>, 
-    headers: HeaderMap, 
+    ClientIp(client_ip): ClientIp,
     Json(payload): Json<authenticate::AuthenticateRequest>
 ) -> Json<serde_json::Value> {
-    // 获取客户端IP
-    let client_ip = extract_client_ip(Some(&ConnectInfo(addr)), &headers);
-    let ip_str = client_ip.as_deref().unwrap_or("unknown");
-    
-    debug!("处理认证请求，来自IP: {}", ip_str);
+    debug!("处理认证请求，来自IP: {}", client_ip);
     
-    let response = authenticate::authenticate_password(&payload.password, client_ip.as_deref()).await;
+    let response = authenticate::authenticate_password(&payload.password, Some(client_ip.as_str())).await;
     Json(json!(response))
 }
 
@@ -256,4 +455,79 @@ async fn list_tokens_handler() -> Json<serde_json::Value> {
 // 简单的ping处理器，用于测量网络延迟
 async fn ping_handler() -> impl IntoResponse {
     "pong"
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use axum::middleware;
+    use tokio::net::TcpListener;
+    use tower_cookies::CookieManagerLayer;
+    use crate::api::client_ip::PeerAddr;
+    use crate::config::test_support::set_test_config;
+    use crate::storage::MemoryStorage;
+
+    // 使用内存存储启动首页和API路由，返回服务地址
+    async fn spawn_app(storage: SharedStorage) -> String {
+        let app = Router::new()
+            .route("/", get(|| async { "home" }).layer(middleware::from_fn_with_state(storage.clone(), visitor::track_visit)))
+            .nest("/api", api_routes())
+            .with_state(storage)
+            .layer(CookieManagerLayer::new());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<PeerAddr>()).await.unwrap()
+        });
+        format!("http://{}", addr)
+    }
+
+    async fn get_json(client: &reqwest::Client, url: String) -> serde_json::Value {
+        client.get(url).send().await.unwrap().json().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn visitor_handlers_use_memory_storage() {
+        let _guard = set_test_config(|_| {}).await;
+        let storage: SharedStorage = Arc::new(MemoryStorage::new());
+        let base = spawn_app(storage.clone()).await;
+        let client = reqwest::Client::builder().user_agent("Mozilla/5.0 Firefox/128.0").build().unwrap();
+
+        assert_eq!(client.get(&base).send().await.unwrap().text().await.unwrap(), "home");
+        assert_eq!(storage.visitor_visits("127.0.0.1").unwrap(), 1);
+
+        let stats = get_json(&client, format!("{}/api/visitor", base)).await;
+        assert_eq!((stats["total_visits"].as_u64(), stats["unique_ips"].as_u64()), (Some(1), Some(1)));
+
+        let current = get_json(&client, format!("{}/api/current-ip", base)).await;
+        assert_eq!((current["ip"].as_str(), current["visits"].as_u64()), (Some("127.0.0.1"), Some(1)));
+
+        let history = get_json(&client, format!("{}/api/visitor/history?granularity=hour&range=24h", base)).await;
+        let points = history["points"].as_array().unwrap();
+        assert_eq!(points.len(), 24);
+        assert_eq!(points[23]["visits"].as_u64(), Some(1));
+        assert_eq!(points[23]["unique_visitors"].as_u64(), Some(1));
+
+        // 没有地理位置的访客计入其他
+        let geo = get_json(&client, format!("{}/api/visitor/geo", base)).await;
+        assert_eq!(geo["success"].as_bool(), Some(true));
+        assert_eq!(geo["other"]["visits"].as_u64(), Some(1));
+
+        let record = get_json(&client, format!("{}/api/visitor/me", base)).await;
+        assert_eq!(record["visits"].as_u64(), Some(1));
+        let deleted: serde_json::Value = client.delete(format!("{}/api/visitor/me", base))
+            .send().await.unwrap().json().await.unwrap();
+        assert_eq!(deleted["success"].as_bool(), Some(true));
+        assert_eq!(storage.unique_visitors().unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn export_requires_authentication() {
+        let _guard = set_test_config(|_| {}).await;
+        let base = spawn_app(Arc::new(MemoryStorage::new())).await;
+
+        let response = reqwest::get(format!("{}/api/visitor/export", base)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,270 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
+use tokio::task::JoinHandle;
+use tokio::time::{interval, timeout, Duration, Instant};
+use rimplog::info;
+use crate::config::{get_config, subscribe_config, MonitorConfig, MonitorKind};
+use crate::db::{self, MonitorCheck};
+
+// 检查记录保留时间（与最长统计窗口一致）
+const RETENTION_SECONDS: u64 = 30 * 86400;
+
+// 监控任务状态
+static MONITORS_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// 各时间窗口的可用率
+#[derive(Serialize, Clone)]
+pub struct UptimeSummary {
+    pub h24: Option<f64>,
+    pub d7: Option<f64>,
+    pub d30: Option<f64>,
+}
+
+// 单个服务的监控汇总
+#[derive(Serialize, Clone)]
+pub struct MonitorSummary {
+    pub name: String,
+    pub kind: MonitorKind,
+    pub up: Option<bool>,
+    pub last_checked: Option<u64>,
+    pub response_time_ms: Option<u64>,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub avg_response_time_ms: Option<u64>,
+    pub uptime: UptimeSummary,
+}
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 启动所有配置的服务监控，每个服务按自己的间隔检查，配置中的监控列表变化时重启检查任务
+pub fn start_monitors() {
+    // 防止重复启动
+    if MONITORS_RUNNING.swap(true, Ordering::SeqCst) {
+        info!("服务监控已在运行中");
+        return;
+    }
+
+    let client = reqwest::Client::new();
+    let mut config_receiver = subscribe_config();
+    let mut monitors = config_receiver.borrow_and_update().monitors.clone();
+    if monitors.is_empty() {
+        info!("未配置服务监控");
+    }
+
+    tokio::spawn(async move {
+        let mut tasks = spawn_monitors(&client, &monitors);
+
+        while config_receiver.changed().await.is_ok() {
+            let updated = config_receiver.borrow_and_update().monitors.clone();
+            if updated == monitors {
+                continue;
+            }
+
+            info!("服务监控配置已变更，重启{}个监控任务", updated.len());
+            for task in tasks.drain(..) {
+                task.abort();
+            }
+            monitors = updated;
+            tasks = spawn_monitors(&client, &monitors);
+        }
+    });
+}
+
+// 为每个服务启动检查任务
+fn spawn_monitors(client: &reqwest::Client, monitors: &[MonitorConfig]) -> Vec<JoinHandle<()>> {
+    monitors.iter().cloned().map(|monitor| {
+        info!("启动服务监控: {} ({:?} {})，间隔：{}秒",
+              monitor.name, monitor.kind, monitor.target, monitor.interval_seconds);
+
+        let client = client.clone();
+        tokio::spawn(async move {
+            let mut interval_timer = interval(Duration::from_secs(monitor.interval_seconds.max(1)));
+            let mut last_success = None;
+
+            loop {
+                interval_timer.tick().await;
+
+                let check = probe(&client, &monitor).await;
+                if last_success.is_some_and(|success| success != check.success) {
+                    info!("服务 {} 状态变更: {}", monitor.name, if check.success { "恢复" } else { "不可用" });
+                }
+                last_success = Some(check.success);
+
+                let name = monitor.name.clone();
+                db::run(move || {
+                    if let Err(e) = db::record_monitor_check(&check) {
+                        info!("保存监控结果失败: {}", e);
+                    }
+                    if let Err(e) = db::purge_monitor_checks(&name, check.timestamp.saturating_sub(RETENTION_SECONDS)) {
+                        info!("清理监控记录失败: {}", e);
+                    }
+                }).await;
+            }
+        })
+    }).collect()
+}
+
+// 对服务执行一次检查
+async fn probe(client: &reqwest::Client, monitor: &MonitorConfig) -> MonitorCheck {
+    let timestamp = now_secs();
+    let limit = Duration::from_millis(monitor.timeout_ms);
+    let start = Instant::now();
+
+    let mut check = MonitorCheck {
+        monitor: monitor.name.clone(),
+        timestamp,
+        success: false,
+        response_time_ms: None,
+        status_code: None,
+        error: None,
+    };
+
+    match monitor.kind {
+        MonitorKind::Http => {
+            match client.get(&monitor.target).timeout(limit).send().await {
+                Ok(response) => {
+                    let status = response.status();
+                    check.response_time_ms = Some(start.elapsed().as_millis() as u64);
+                    check.status_code = Some(status.as_u16());
+                    check.success = match monitor.expected_status {
+                        Some(expected) => status.as_u16() == expected,
+                        None => status.is_success(),
+                    };
+                    if !check.success {
+                        check.error = Some(format!("状态码 {}", status.as_u16()));
+                    }
+                }
+                Err(e) => {
+                    check.error = Some(if e.is_timeout() { "请求超时".to_string() } else { e.to_string() });
+                }
+            }
+        }
+        MonitorKind::Tcp => {
+            match timeout(limit, TcpStream::connect(&monitor.target)).await {
+                Ok(Ok(_)) => {
+                    check.response_time_ms = Some(start.elapsed().as_millis() as u64);
+                    check.success = true;
+                }
+                Ok(Err(e)) => check.error = Some(e.to_string()),
+                Err(_) => check.error = Some("连接超时".to_string()),
+            }
+        }
+    }
+
+    check
+}
+
+// 计算指定时间窗口内的可用率（百分比）
+fn uptime_since(monitor: &str, since: u64) -> (Option<f64>, Option<f64>) {
+    match db::get_monitor_uptime(monitor, since) {
+        Ok((total, successes, avg)) if total > 0 => {
+            let uptime = (successes as f64 / total as f64 * 10000.0).round() / 100.0;
+            (Some(uptime), avg)
+        }
+        _ => (None, None),
+    }
+}
+
+// 获取所有服务的监控汇总
+pub fn get_monitor_summaries() -> Vec<MonitorSummary> {
+    let now = now_secs();
+
+    get_config().monitors.iter().map(|monitor| {
+        let latest = db::get_latest_monitor_check(&monitor.name).ok().flatten();
+        let (h24, avg_24h) = uptime_since(&monitor.name, now.saturating_sub(86400));
+        let (d7, _) = uptime_since(&monitor.name, now.saturating_sub(7 * 86400));
+        let (d30, _) = uptime_since(&monitor.name, now.saturating_sub(30 * 86400));
+
+        MonitorSummary {
+            name: monitor.name.clone(),
+            kind: monitor.kind,
+            up: latest.as_ref().map(|check| check.success),
+            last_checked: latest.as_ref().map(|check| check.timestamp),
+            response_time_ms: latest.as_ref().and_then(|check| check.response_time_ms),
+            status_code: latest.as_ref().and_then(|check| check.status_code),
+            error: latest.and_then(|check| check.error),
+            avg_response_time_ms: avg_24h.map(|avg| avg.round() as u64),
+            uptime: UptimeSummary { h24, d7, d30 },
+        }
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::get, Router};
+    use tokio::net::TcpListener;
+
+    // 启动一个本地HTTP服务，/ok 返回200，/missing 返回404
+    async fn spawn_server() -> String {
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route("/missing", get(|| async { StatusCode::NOT_FOUND }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        addr.to_string()
+    }
+
+    // 获取一个没有进程监听的本地端口
+    async fn closed_addr() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        listener.local_addr().unwrap().to_string()
+    }
+
+    fn monitor(kind: MonitorKind, target: String, expected_status: Option<u16>) -> MonitorConfig {
+        MonitorConfig {
+            name: "test".to_string(),
+            kind,
+            target,
+            interval_seconds: 60,
+            timeout_ms: 2000,
+            expected_status,
+        }
+    }
+
+    #[tokio::test]
+    async fn http_probe_reports_up_and_down() {
+        let client = reqwest::Client::new();
+        let addr = spawn_server().await;
+
+        let check = probe(&client, &monitor(MonitorKind::Http, format!("http://{}/ok", addr), None)).await;
+        assert!(check.success);
+        assert_eq!(check.status_code, Some(200));
+        assert!(check.response_time_ms.is_some());
+        assert!(check.error.is_none());
+
+        let check = probe(&client, &monitor(MonitorKind::Http, format!("http://{}/missing", addr), None)).await;
+        assert!(!check.success);
+        assert_eq!(check.status_code, Some(404));
+
+        let check = probe(&client, &monitor(MonitorKind::Http, format!("http://{}/missing", addr), Some(404))).await;
+        assert!(check.success);
+
+        let check = probe(&client, &monitor(MonitorKind::Http, format!("http://{}/ok", closed_addr().await), None)).await;
+        assert!(!check.success);
+        assert!(check.status_code.is_none());
+        assert!(check.error.is_some());
+    }
+
+    #[tokio::test]
+    async fn tcp_probe_reports_up_and_down() {
+        let client = reqwest::Client::new();
+
+        let check = probe(&client, &monitor(MonitorKind::Tcp, spawn_server().await, None)).await;
+        assert!(check.success);
+        assert!(check.response_time_ms.is_some());
+
+        let check = probe(&client, &monitor(MonitorKind::Tcp, closed_addr().await, None)).await;
+        assert!(!check.success);
+        assert!(check.error.is_some());
+    }
+}
@@ -0,0 +1,98 @@
+use ipnet::IpNet;
+use once_cell::sync::Lazy;
+use rimplog::info;
+use sha2::{Digest, Sha256};
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+use crate::config::{get_config, IpMode};
+use crate::db;
+use crate::storage::Storage;
+
+// 截断模式下保留的前缀长度
+const IPV4_PREFIX: u8 = 24;
+const IPV6_PREFIX: u8 = 48;
+
+// 当前周期的盐值 (周期编号, 盐值)
+static CURRENT_SALT: Lazy<Mutex<Option<(u64, String)>>> = Lazy::new(|| Mutex::new(None));
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 获取当前周期的盐值，周期切换时生成新盐值并删除旧盐值，旧的哈希无法再与IP关联
+fn current_salt() -> Option<String> {
+    let rotation = get_config().privacy.salt_rotation_hours.max(1) * 3600;
+    let period = now_secs() / rotation;
+
+    let mut current = CURRENT_SALT.lock().ok()?;
+    if let Some((cached_period, salt)) = current.as_ref() {
+        if *cached_period == period {
+            return Some(salt.clone());
+        }
+    }
+
+    // 盐值保存在数据库中，重启后同一周期内的哈希保持一致
+    let candidate = Uuid::new_v4().simple().to_string();
+    match db::get_or_create_privacy_salt(period, &candidate) {
+        Ok(salt) => {
+            *current = Some((period, salt.clone()));
+            Some(salt)
+        }
+        Err(e) => {
+            info!("获取隐私盐值失败: {}", e);
+            None
+        }
+    }
+}
+
+// 计算加盐哈希
+fn hash_ip(ip: &str) -> Option<String> {
+    let salt = current_salt()?;
+    let digest = Sha256::new()
+        .chain_update(salt.as_bytes())
+        .chain_update(ip.as_bytes())
+        .finalize();
+    let hex: String = digest[..16].iter().map(|byte| format!("{:02x}", byte)).collect();
+    Some(format!("h:{}", hex))
+}
+
+// 截断IP，IPv4保留/24，IPv6保留/48
+fn truncate_ip(ip: &str) -> Option<String> {
+    let ip: IpAddr = ip.parse().ok()?;
+    let prefix = if ip.is_ipv4() { IPV4_PREFIX } else { IPV6_PREFIX };
+    let network = IpNet::new(ip, prefix).ok()?.trunc();
+    Some(network.to_string())
+}
+
+// 根据隐私设置将IP转换为存储用的访客标识
+pub fn visitor_key(ip: &str) -> String {
+    let key = match get_config().privacy.ip_mode {
+        IpMode::Full => return ip.to_string(),
+        IpMode::Hash => hash_ip(ip),
+        IpMode::Truncate => truncate_ip(ip),
+    };
+
+    // 无法转换时不保存原始IP
+    key.unwrap_or_else(|| "unknown".to_string())
+}
+
+// 按保留策略清理或匿名化过期的访客记录
+pub fn purge_expired_visitors(storage: &dyn Storage) {
+    let privacy_config = &get_config().privacy;
+    if privacy_config.retention_days == 0 {
+        return;
+    }
+
+    let before = now_secs().saturating_sub(privacy_config.retention_days * 86400);
+    match storage.expire_visitors(before, privacy_config.retention_action) {
+        Ok(0) => {}
+        Ok(count) => info!("已按保留策略处理{}条过期访客记录", count),
+        Err(e) => info!("清理过期访客记录失败: {}", e),
+    }
+}
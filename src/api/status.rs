@@ -1,9 +1,16 @@
 use serde::{Serialize, Deserialize};
 use std::time::{SystemTime, UNIX_EPOCH};
 use sysinfo::System;
-use std::sync::{OnceLock, Mutex};
-use crate::config::get_server_config;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::collections::HashMap;
+use once_cell::sync::Lazy;
+use tokio::time::{interval, Duration, Instant};
+use tokio::sync::broadcast;
+use crate::config::{self, get_config, get_server_config, subscribe_config, NodeConfig};
 use crate::db;
+use crate::api::incident::{self, IncidentsSummary};
+use crate::storage::{SharedStorage, Storage};
 use rimplog::info;
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -27,18 +34,15 @@ pub struct VisitorStatsConfig {
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct AuthConfig {
-    pub password: String,
     pub token_expiration_seconds: u64,
 }
 
-// 导出SERVER_CONFIG以便在config模块中可以访问
-pub static SERVER_CONFIG: OnceLock<Mutex<ServerConfig>> = OnceLock::new();
-
 #[derive(Serialize, Deserialize)]
 pub struct StatusResponse {
     server: ServerConfig,
     system: SystemStatus,
     visitor_stats: Option<VisitorStats>,
+    incidents: IncidentsSummary,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -61,108 +65,48 @@ pub struct MemoryUsage {
 pub struct VisitorStats {
     total_visits: u64,
     unique_ips: usize,
+    #[serde(default)]
+    bot_visits: u64,
 }
 
-// 初始化服务器配置
-pub fn init_server_config() {
-    let config = get_server_config();
-    let server_config = ServerConfig {
-        name: config.name.clone(),
-        status: config.status.clone(),
-        message: config.message.clone(),
-        title: config.title.clone(),
-        subtitle: config.subtitle.clone(),
-        show_visitor_stats: VisitorStatsConfig {
-            enabled: config.show_visitor_stats.enabled,
-            show_total_visits: config.show_visitor_stats.show_total_visits,
-            show_unique_ips: config.show_visitor_stats.show_unique_ips,
-            show_personal_visits: config.show_visitor_stats.show_personal_visits,
-        },
-        auth: AuthConfig {
-            password: config.auth.password.clone(),
-            token_expiration_seconds: config.auth.token_expiration_seconds,
-        },
-    };
-    
-    // 初始化为Mutex包装的ServerConfig
-    SERVER_CONFIG.get_or_init(|| Mutex::new(server_config));
+// 节点状态，由后台轮询任务更新
+#[derive(Serialize, Clone, Debug)]
+pub struct NodeStatus {
+    pub name: String,
+    pub url: String,
+    pub local: bool,
+    pub status: String,
+    pub message: String,
+    pub online: bool,
+    pub latency_ms: Option<u64>,
+    pub show_latency: bool,
+    pub availability: f64,
+    pub checks: u64,
+    pub successes: u64,
+    pub last_checked: u64,
 }
 
-// 更新服务器状态
-pub fn update_server_status(server_config: ServerConfig) {
-    if let Some(mutex) = SERVER_CONFIG.get() {
-        // 获取锁并更新内部值
-        if let Ok(mut config) = mutex.lock() {
-            // 记录关键配置信息的变更
-            if config.auth.password != server_config.auth.password {
-                info!("更新系统密码: {} -> {}", config.auth.password, server_config.auth.password);
-            }
-            if config.auth.token_expiration_seconds != server_config.auth.token_expiration_seconds {
-                info!("更新令牌过期时间: {}秒 -> {}秒", 
-                     config.auth.token_expiration_seconds, 
-                     server_config.auth.token_expiration_seconds);
-            }
-            
-            // 更新配置
-            *config = server_config;
-        } else {
-            info!("无法获取服务器配置锁，更新失败");
-        }
-    } else {
-        // 如果尚未初始化，则进行初始化
-        SERVER_CONFIG.get_or_init(|| Mutex::new(server_config));
-        info!("服务器配置已初始化");
-    }
+// 按URL保存对等节点的最近状态
+static NODE_STATUS: Lazy<Mutex<HashMap<String, NodeStatus>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 节点轮询任务状态
+static NODE_POLLER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// 状态推送事件
+#[derive(Clone, Debug)]
+pub struct StatusEvent {
+    pub event: &'static str,
+    pub data: serde_json::Value,
 }
 
-// 修改 get_status 函数，使用缓存的SERVER_CONFIG而不是每次从文件读取
-pub async fn get_status() -> StatusResponse {
-    // 获取系统状态
-    let mut sys = System::new_all();
-    sys.refresh_all();
+// 状态事件广播通道，SSE连接各自订阅
+static STATUS_EVENTS: Lazy<broadcast::Sender<StatusEvent>> = Lazy::new(|| broadcast::channel(64).0);
 
-    let memory = sys.used_memory();
-    let total_memory = sys.total_memory();
-    let free_memory = sys.free_memory();
-    let cpu_usage = sys.global_cpu_usage();
-    
-    // 获取当前时间戳
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-    
-    // 获取系统启动时间
-    let uptime = sysinfo::System::uptime();
-    
-    // 使用缓存的SERVER_CONFIG
-    let server_config = if let Some(mutex) = SERVER_CONFIG.get() {
-        if let Ok(config) = mutex.lock() {
-            config.clone()
-        } else {
-            // 如果锁定失败，回退到从文件读取
-            let config = get_server_config();
-            ServerConfig {
-                name: config.name.clone(),
-                status: config.status.clone(),
-                message: config.message.clone(),
-                title: config.title.clone(),
-                subtitle: config.subtitle.clone(),
-                show_visitor_stats: VisitorStatsConfig {
-                    enabled: config.show_visitor_stats.enabled,
-                    show_total_visits: config.show_visitor_stats.show_total_visits,
-                    show_unique_ips: config.show_visitor_stats.show_unique_ips,
-                    show_personal_visits: config.show_visitor_stats.show_personal_visits,
-                },
-                auth: AuthConfig {
-                    password: config.auth.password.clone(),
-                    token_expiration_seconds: config.auth.token_expiration_seconds,
-                },
-            }
-        }
-    } else {
-        // 如果SERVER_CONFIG未初始化，直接从文件读取
-        let config = get_server_config();
+// 系统状态推送任务状态
+static STATUS_PUSHER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+impl From<&config::ServerConfig> for ServerConfig {
+    fn from(config: &config::ServerConfig) -> Self {
         ServerConfig {
             name: config.name.clone(),
             status: config.status.clone(),
@@ -176,40 +120,366 @@ pub async fn get_status() -> StatusResponse {
                 show_personal_visits: config.show_visitor_stats.show_personal_visits,
             },
             auth: AuthConfig {
-                password: config.auth.password.clone(),
                 token_expiration_seconds: config.auth.token_expiration_seconds,
             },
         }
-    };
+    }
+}
+
+// 从已刷新的System实例中收集系统状态
+fn collect_system_status(sys: &System) -> SystemStatus {
+    // 获取当前时间戳
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
     
-    // 获取访问者统计
-    let visitor_stats = if get_server_config().show_visitor_stats.enabled {
+    SystemStatus {
+        cpu_usage: sys.global_cpu_usage(),
+        memory_usage: MemoryUsage {
+            used: sys.used_memory(),
+            total: sys.total_memory(),
+            free: sys.free_memory(),
+        },
+        platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
+        timestamp,
+        // 获取系统启动时间
+        uptime: sysinfo::System::uptime(),
+    }
+}
+
+// 获取访问者统计，未启用时返回None
+fn collect_visitor_stats(storage: &dyn Storage) -> Option<VisitorStats> {
+    if get_config().server.show_visitor_stats.enabled {
         // 使用数据库API获取访问统计
-        let total_visits = db::get_total_visits().unwrap_or(0);
-        let unique_ips = db::get_unique_ip_count().unwrap_or(0);
+        let total_visits = storage.total_visits().unwrap_or(0);
+        let unique_ips = storage.unique_visitors().unwrap_or(0);
+        let bot_visits = storage.bot_visits().unwrap_or(0);
         
-        let visitor_stats = VisitorStats {
+        Some(VisitorStats {
             total_visits,
             unique_ips,
-        };
-        Some(visitor_stats)
+            bot_visits,
+        })
     } else {
         None
-    };
+    }
+}
+
+// 获取当前状态，服务器信息取自当前配置
+pub async fn get_status(storage: SharedStorage) -> StatusResponse {
+    // 获取系统状态
+    let mut sys = System::new_all();
+    sys.refresh_all();
+    
+    let server_config = ServerConfig::from(&get_config().server);
+    
+    let (visitor_stats, incidents) = db::run(move || {
+        (collect_visitor_stats(&*storage), incident::get_incidents_summary())
+    }).await;
     
     StatusResponse {
         server: server_config,
-        system: SystemStatus {
-            cpu_usage,
-            memory_usage: MemoryUsage {
-                used: memory,
-                total: total_memory,
-                free: free_memory,
-            },
-            platform: format!("{} {}", std::env::consts::OS, std::env::consts::ARCH),
-            timestamp,
-            uptime,
-        },
+        system: collect_system_status(&sys),
         visitor_stats,
+        incidents,
+    }
+}
+
+// 启动对等节点状态轮询任务
+pub fn start_node_poller() {
+    // 防止重复启动
+    if NODE_POLLER_RUNNING.swap(true, Ordering::SeqCst) {
+        info!("节点轮询任务已在运行中");
+        return;
+    }
+    
+    let interval_secs = get_config().node_poller.interval_seconds.max(1);
+    info!("启动节点状态轮询，间隔：{}秒", interval_secs);
+    
+    tokio::spawn(async move {
+        let client = reqwest::Client::new();
+        let mut interval_timer = interval(Duration::from_secs(interval_secs));
+        
+        loop {
+            interval_timer.tick().await;
+            poll_nodes(&client).await;
+        }
+    });
+}
+
+// 并发检查所有配置的节点并更新状态
+async fn poll_nodes(client: &reqwest::Client) {
+    let config = get_config();
+    let timeout = Duration::from_millis(config.node_poller.timeout_ms);
+    
+    let checks = config.nodes.iter().map(|node| check_node(client, node, timeout));
+    let results = futures::future::join_all(checks).await;
+    
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    
+    if let Ok(mut nodes) = NODE_STATUS.lock() {
+        // 移除已不在配置中的节点
+        nodes.retain(|url, _| config.nodes.iter().any(|node| &node.url == url));
+        
+        for (node, (status, message, online, latency_ms)) in config.nodes.iter().zip(results) {
+            let entry = nodes.entry(node.url.clone()).or_insert_with(|| NodeStatus {
+                name: node.name.clone(),
+                url: node.url.clone(),
+                local: false,
+                status: String::new(),
+                message: String::new(),
+                online: false,
+                latency_ms: None,
+                show_latency: node.show_latency,
+                availability: 0.0,
+                checks: 0,
+                successes: 0,
+                last_checked: 0,
+            });
+            
+            if entry.online != online && entry.checks > 0 {
+                info!("节点 {} 状态变更: {}", node.name, if online { "在线" } else { "离线" });
+            }
+            
+            entry.name = node.name.clone();
+            entry.show_latency = node.show_latency;
+            entry.status = status;
+            entry.message = message;
+            entry.online = online;
+            entry.latency_ms = latency_ms;
+            entry.checks += 1;
+            if online {
+                entry.successes += 1;
+            }
+            entry.availability = (entry.successes as f64 / entry.checks as f64 * 10000.0).round() / 100.0;
+            entry.last_checked = timestamp;
+        }
+    }
+}
+
+// 检查单个节点，返回 (状态, 消息, 是否在线, 延迟)
+async fn check_node(client: &reqwest::Client, node: &NodeConfig, timeout: Duration) -> (String, String, bool, Option<u64>) {
+    let start = Instant::now();
+    
+    let response = client
+        .get(&node.url)
+        .header("Accept", "application/json")
+        .timeout(timeout)
+        .send()
+        .await;
+    
+    let response = match response {
+        Ok(response) => response,
+        Err(_) => return ("error".to_string(), "服务器无法连接".to_string(), false, None),
+    };
+    let latency_ms = Some(start.elapsed().as_millis() as u64);
+    
+    // 能连接但状态接口不可用时视为维护状态
+    let unavailable = (
+        "maintenance".to_string(),
+        "服务器可连接，但状态API不可用".to_string(),
+        true,
+        latency_ms,
+    );
+    
+    if !response.status().is_success() {
+        return unavailable;
+    }
+    
+    match response.json::<serde_json::Value>().await {
+        Ok(data) => {
+            let server = &data["server"];
+            match server["status"].as_str() {
+                Some(status) => (
+                    status.to_string(),
+                    server["message"].as_str().unwrap_or("无法获取服务器状态").to_string(),
+                    true,
+                    latency_ms,
+                ),
+                None => unavailable,
+            }
+        }
+        Err(_) => unavailable,
     }
-}
\ No newline at end of file
+}
+
+// 获取当前服务器及所有对等节点的状态
+pub fn get_nodes() -> Vec<NodeStatus> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    
+    // 当前服务器总是第一个
+    let config = get_server_config();
+    let (name, status, message) = (config.name, config.status, config.message);
+    
+    let mut result = vec![NodeStatus {
+        name,
+        url: "/api/status".to_string(),
+        local: true,
+        status,
+        message,
+        online: true,
+        latency_ms: None,
+        show_latency: true,
+        availability: 100.0,
+        checks: 0,
+        successes: 0,
+        last_checked: timestamp,
+    }];
+    
+    // 按配置顺序返回对等节点，尚未检查的节点标记为未知
+    let nodes = NODE_STATUS.lock().map(|nodes| nodes.clone()).unwrap_or_default();
+    for node in get_config().nodes.iter() {
+        result.push(nodes.get(&node.url).cloned().unwrap_or_else(|| NodeStatus {
+            name: node.name.clone(),
+            url: node.url.clone(),
+            local: false,
+            status: "unknown".to_string(),
+            message: "正在检查节点状态".to_string(),
+            online: false,
+            latency_ms: None,
+            show_latency: node.show_latency,
+            availability: 0.0,
+            checks: 0,
+            successes: 0,
+            last_checked: 0,
+        }));
+    }
+    
+    result
+}
+
+// 发布状态事件，没有订阅者时直接丢弃
+pub fn publish_event(event: &'static str, data: serde_json::Value) {
+    let _ = STATUS_EVENTS.send(StatusEvent { event, data });
+}
+
+// 订阅状态事件
+pub fn subscribe_events() -> broadcast::Receiver<StatusEvent> {
+    STATUS_EVENTS.subscribe()
+}
+
+// 推送最新的访问统计
+pub fn publish_visitor_stats(storage: &dyn Storage) {
+    if STATUS_EVENTS.receiver_count() == 0 {
+        return;
+    }
+    
+    if let Some(visitor_stats) = collect_visitor_stats(storage) {
+        publish_event("visitors", serde_json::json!(visitor_stats));
+    }
+}
+
+// 启动系统状态定时推送任务
+pub fn start_status_pusher() {
+    // 防止重复启动
+    if STATUS_PUSHER_RUNNING.swap(true, Ordering::SeqCst) {
+        info!("状态推送任务已在运行中");
+        return;
+    }
+    
+    let interval_secs = get_config().status_stream.push_interval_seconds.max(1);
+    info!("启动状态推送，间隔：{}秒", interval_secs);
+    
+    // 配置重新加载后推送新的服务器信息
+    let mut config_receiver = subscribe_config();
+    tokio::spawn(async move {
+        while config_receiver.changed().await.is_ok() {
+            let server_config = ServerConfig::from(&config_receiver.borrow_and_update().server);
+            publish_event("config", serde_json::json!(server_config));
+        }
+    });
+    
+    tokio::spawn(async move {
+        // 保持同一个System实例，使CPU使用率在两次刷新之间有意义
+        let mut sys = System::new();
+        let mut interval_timer = interval(Duration::from_secs(interval_secs));
+        
+        loop {
+            interval_timer.tick().await;
+            
+            // 没有客户端连接时不采集
+            if STATUS_EVENTS.receiver_count() == 0 {
+                continue;
+            }
+            
+            sys.refresh_cpu_usage();
+            sys.refresh_memory();
+            publish_event("system", serde_json::json!(collect_system_status(&sys)));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::test_support::set_test_config;
+    use axum::{routing::get, Json, Router};
+    use tokio::net::TcpListener;
+
+    // 启动一个返回固定状态的对等节点，返回其状态接口地址
+    async fn spawn_peer() -> String {
+        let app = Router::new().route("/api/status", get(|| async {
+            Json(serde_json::json!({
+                "server": { "status": "running", "message": "peer ok" }
+            }))
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{}/api/status", addr)
+    }
+
+    // 获取一个当前没有监听的端口
+    async fn closed_url() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{}/api/status", addr)
+    }
+
+    #[tokio::test]
+    async fn polls_local_peers() {
+        let online_url = spawn_peer().await;
+        let offline_url = closed_url().await;
+        let nodes = vec![
+            NodeConfig { name: "peer".to_string(), url: online_url.clone(), show_latency: true },
+            NodeConfig { name: "down".to_string(), url: offline_url.clone(), show_latency: false },
+        ];
+        let _guard = set_test_config(|config| {
+            config.nodes = nodes;
+            config.node_poller.timeout_ms = 2000;
+        }).await;
+
+        let client = reqwest::Client::new();
+        poll_nodes(&client).await;
+        poll_nodes(&client).await;
+
+        let result = get_nodes();
+        assert_eq!(result.len(), 3);
+        assert!(result[0].local);
+
+        let peer = &result[1];
+        assert_eq!(peer.url, online_url);
+        assert!(peer.online);
+        assert_eq!(peer.status, "running");
+        assert_eq!(peer.message, "peer ok");
+        assert!(peer.latency_ms.is_some());
+        assert_eq!(peer.checks, 2);
+        assert_eq!(peer.availability, 100.0);
+
+        let down = &result[2];
+        assert_eq!(down.url, offline_url);
+        assert!(!down.online);
+        assert_eq!(down.status, "error");
+        assert!(down.latency_ms.is_none());
+        assert_eq!(down.checks, 2);
+        assert_eq!(down.availability, 0.0);
+    }
+}
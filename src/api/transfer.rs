@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::db::{ImportSummary, IpVisitExport};
+use crate::storage::Storage;
+
+// 导出格式版本
+const EXPORT_VERSION: u32 = 1;
+
+// CSV导出中记录总访问次数的注释行前缀
+const CSV_TOTAL_PREFIX: &str = "# total_visits=";
+
+// 导出导入格式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferFormat {
+    Json,
+    Csv,
+}
+
+impl TransferFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "csv" => Some(Self::Csv),
+            _ => None,
+        }
+    }
+
+    // 根据文件扩展名判断格式，默认JSON
+    pub fn from_path(path: &str) -> Self {
+        if path.to_lowercase().ends_with(".csv") {
+            Self::Csv
+        } else {
+            Self::Json
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Csv => "text/csv; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Csv => "csv",
+        }
+    }
+}
+
+// JSON导出内容
+#[derive(Serialize, Deserialize)]
+pub struct VisitorExport {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: u64,
+    pub total_visits: u64,
+    pub ip_visits: Vec<IpVisitExport>,
+}
+
+// 导出访客统计
+pub fn export_visitors(storage: &dyn Storage, format: TransferFormat) -> Result<String, String> {
+    let (total_visits, ip_visits) = storage.export_visits()?;
+
+    match format {
+        TransferFormat::Json => {
+            let export = VisitorExport {
+                version: EXPORT_VERSION,
+                exported_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                total_visits,
+                ip_visits,
+            };
+            serde_json::to_string_pretty(&export).map_err(|e| format!("生成JSON失败: {}", e))
+        }
+        TransferFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for record in ip_visits.iter() {
+                writer.serialize(record).map_err(|e| format!("生成CSV失败: {}", e))?;
+            }
+            let body = writer.into_inner().map_err(|e| format!("生成CSV失败: {}", e))?;
+            let body = String::from_utf8(body).map_err(|e| format!("生成CSV失败: {}", e))?;
+
+            // 第一行记录总访问次数，其余为访客记录
+            Ok(format!("{}{}\n{}", CSV_TOTAL_PREFIX, total_visits, body))
+        }
+    }
+}
+
+// 解析导出内容，返回 (总访问次数, 访客记录)
+fn parse_export(format: TransferFormat, content: &str) -> Result<(u64, Vec<IpVisitExport>), String> {
+    match format {
+        TransferFormat::Json => {
+            let export: VisitorExport = serde_json::from_str(content)
+                .map_err(|e| format!("解析JSON失败: {}", e))?;
+            if export.version > EXPORT_VERSION {
+                return Err(format!("不支持的导出版本: {}", export.version));
+            }
+            Ok((export.total_visits, export.ip_visits))
+        }
+        TransferFormat::Csv => {
+            let content = content.trim_start_matches('\u{feff}');
+            let (total_visits, body) = match content.strip_prefix(CSV_TOTAL_PREFIX) {
+                Some(rest) => {
+                    let (line, body) = rest.split_once('\n').unwrap_or((rest, ""));
+                    let total = line.trim().parse::<u64>()
+                        .map_err(|_| format!("无效的总访问次数: {}", line.trim()))?;
+                    (Some(total), body)
+                }
+                None => (None, content),
+            };
+
+            let mut reader = csv::Reader::from_reader(body.as_bytes());
+            let mut records = Vec::new();
+            for (index, record) in reader.deserialize::<IpVisitExport>().enumerate() {
+                // 行号包含注释行和表头
+                records.push(record.map_err(|e| format!("解析CSV第{}行失败: {}", index + 3, e))?);
+            }
+
+            // 没有总访问次数时按访客记录的访问次数之和计算
+            let total_visits = total_visits
+                .unwrap_or_else(|| records.iter().map(|record| record.visit_count).sum());
+            Ok((total_visits, records))
+        }
+    }
+}
+
+// 导入并合并访客统计
+pub fn import_visitors(storage: &dyn Storage, format: TransferFormat, content: &str) -> Result<ImportSummary, String> {
+    let (total_visits, records) = parse_export(format, content)?;
+
+    if records.iter().any(|record| record.ip.trim().is_empty()) {
+        return Err("导入数据中存在空的IP".to_string());
+    }
+
+    storage.import_visits(total_visits, &records).map_err(|e| format!("导入访客数据失败: {}", e))
+}
@@ -1,24 +1,56 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use axum::http::Request;
-use axum::extract::ConnectInfo;
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use once_cell::sync::Lazy;
+use tower_cookies::{Cookie, Cookies, cookie::SameSite};
+use uuid::Uuid;
+use axum::http::{header::USER_AGENT, Method, Request};
+use axum::extract::{ConnectInfo, State};
+use axum::middleware::Next;
+use axum::response::Response;
 use serde::{Serialize, Deserialize};
 use rimplog::info;
-use regex;
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::time::{interval, Duration};
 use crate::db;
+use crate::api::{analytics, client_ip, privacy, status};
+use crate::api::client_ip::{ClientIp, PeerAddr};
+use crate::geoip::{self, GeoLocation};
+use crate::storage::{SharedStorage, Storage};
+use crate::api::metrics::parse_range;
+use crate::config::get_config;
+
+// 内置的爬虫User-Agent关键字（小写）
+const BOT_USER_AGENTS: [&str; 24] = [
+    "bot", "crawler", "spider", "crawl", "slurp", "archiver", "facebookexternalhit",
+    "embedly", "headless", "phantomjs", "lighthouse", "preview", "curl", "wget",
+    "python-requests", "python-urllib", "go-http-client", "java/", "okhttp", "libwww",
+    "httpclient", "scrapy", "node-fetch", "uptime",
+];
+
+// 去重表最多保存的记录数
+const MAX_RECENT_VISITS: usize = 100_000;
+
+// 访问时间序列最多返回的时间桶数
+const MAX_HISTORY_POINTS: u64 = 5000;
+
+// 去重窗口内最近出现的访客（ip:/cookie: -> 最后出现时间）
+static RECENT_VISITS: Lazy<Mutex<HashMap<String, u64>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// 上次推送访问统计的时间
+static LAST_STATS_PUSH: AtomicU64 = AtomicU64::new(0);
 
 // 定时保存任务状态
 static TIMER_RUNNING: AtomicBool = AtomicBool::new(false);
 
 // 初始化访问者统计
 pub fn init_visitor_stats() {
-    info!("访问者统计已初始化（使用数据库存储）");
+    info!("访问者统计已初始化");
 }
 
 // 启动定时保存功能
 #[allow(dead_code)]
-pub fn start_periodic_save(interval_secs: u64) {
+pub fn start_periodic_save(storage: SharedStorage, interval_secs: u64) {
     // 防止重复启动
     if TIMER_RUNNING.swap(true, Ordering::SeqCst) {
         info!("定时保存任务已在运行中");
@@ -34,16 +66,196 @@ pub fn start_periodic_save(interval_secs: u64) {
         loop {
             // 等待下一个间隔
             interval_timer.tick().await;
-            info!("数据库已自动持久化访问者统计数据");
+            
+            // 清理过期的去重记录
+            prune_recent_visits();
+            
+            let storage = storage.clone();
+            db::run(move || {
+                // 写入累计的访问计数
+                save_stats(&*storage);
+                
+                // 按隐私保留策略处理过期访客记录
+                privacy::purge_expired_visitors(&*storage);
+                
+                // 清理超出保留期限的访问时间序列
+                let history_config = &get_config().visitor_history;
+                let now = now_secs();
+                let hour_before = now.saturating_sub(history_config.hour_retention_days.saturating_mul(86400));
+                let day_before = now.saturating_sub(history_config.day_retention_days.saturating_mul(86400));
+                if let Err(e) = storage.purge_visit_history(hour_before, day_before) {
+                    info!("清理访问时间序列失败: {}", e);
+                }
+            }).await;
+            
+            // 清理过期的页面和来源统计
+            analytics::purge_page_views().await;
         }
     });
 }
 
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 访问历史查询参数
+#[derive(Deserialize)]
+pub struct VisitorHistoryQuery {
+    pub granularity: Option<String>,
+    pub range: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct VisitorHistoryPoint {
+    timestamp: u64,
+    visits: u64,
+    unique_visitors: u64,
+}
+
+#[derive(Serialize)]
+pub struct VisitorHistoryResponse {
+    pub success: bool,
+    pub message: Option<String>,
+    pub granularity: String,
+    pub range: String,
+    pub points: Vec<VisitorHistoryPoint>,
+}
+
+// 查询访问时间序列，没有访问的时间桶补零
+pub fn get_visit_history(storage: &dyn Storage, query: &VisitorHistoryQuery) -> VisitorHistoryResponse {
+    let granularity = query.granularity.clone().unwrap_or_else(|| "day".to_string()).to_lowercase();
+    let range = query.range.clone().unwrap_or_else(|| "30d".to_string()).to_lowercase();
+
+    let mut response = VisitorHistoryResponse {
+        success: false,
+        message: None,
+        granularity: granularity.clone(),
+        range: range.clone(),
+        points: Vec::new(),
+    };
+
+    let history_config = &get_config().visitor_history;
+    let (size, retention_days) = match granularity.as_str() {
+        "hour" => (3600, history_config.hour_retention_days),
+        "day" => (86400, history_config.day_retention_days),
+        _ => {
+            response.message = Some("未知的时间粒度，可用: hour, day".to_string());
+            return response;
+        }
+    };
+
+    let seconds = match parse_range(&range) {
+        Some(seconds) => seconds
+            .min(retention_days.saturating_mul(86400))
+            .min(size * MAX_HISTORY_POINTS),
+        None => {
+            response.message = Some("无效的时间范围，例如: 24h, 7d, 30d".to_string());
+            return response;
+        }
+    };
+
+    let now = now_secs();
+    let current = now - now % size;
+    let since = current.saturating_sub(seconds.saturating_sub(1) / size * size);
+
+    match storage.visit_history(&granularity, since) {
+        Ok(rows) => {
+            let mut rows = rows.into_iter().peekable();
+            let mut bucket = since;
+            while bucket <= current {
+                let (visits, unique_visitors) = match rows.next_if(|(timestamp, _, _)| *timestamp == bucket) {
+                    Some((_, visits, unique)) => (visits, unique),
+                    None => (0, 0),
+                };
+                response.points.push(VisitorHistoryPoint {
+                    timestamp: bucket,
+                    visits,
+                    unique_visitors,
+                });
+                bucket += size;
+            }
+            response.success = true;
+        }
+        Err(e) => {
+            response.message = Some(format!("获取访问历史失败: {}", e));
+        }
+    }
+
+    response
+}
+
+// 地理分布查询参数
+#[derive(Deserialize)]
+pub struct GeoQuery {
+    pub level: Option<String>,
+    pub min_count: Option<u64>,
+}
+
+#[derive(Serialize)]
+pub struct GeoOther {
+    visits: u64,
+    unique_ips: u64,
+}
+
+#[derive(Serialize)]
+pub struct GeoResponse {
+    pub success: bool,
+    pub message: Option<String>,
+    pub level: String,
+    pub min_count: u64,
+    pub groups: Vec<db::GeoGroup>,
+    pub other: GeoOther,
+}
+
+// 查询访客地理分布，阈值不能低于配置的最小值
+pub fn get_geo_breakdown(storage: &dyn Storage, query: &GeoQuery) -> GeoResponse {
+    let level = query.level.clone().unwrap_or_else(|| "country".to_string()).to_lowercase();
+    let configured_min = get_config().privacy.geo_min_count.max(1);
+    let min_count = query.min_count.unwrap_or(configured_min).max(configured_min);
+
+    let mut response = GeoResponse {
+        success: false,
+        message: None,
+        level: level.clone(),
+        min_count,
+        groups: Vec::new(),
+        other: GeoOther { visits: 0, unique_ips: 0 },
+    };
+
+    if !["continent", "country", "city"].contains(&level.as_str()) {
+        response.message = Some("未知的地理层级，可用: continent, country, city".to_string());
+        return response;
+    }
+
+    match storage.geo_breakdown(&level, min_count) {
+        Ok((groups, other_visits, other_ips)) => {
+            response.success = true;
+            response.groups = groups.into_iter().map(|mut group| {
+                // 坐标保留两位小数
+                group.latitude = group.latitude.map(|value| (value * 100.0).round() / 100.0);
+                group.longitude = group.longitude.map(|value| (value * 100.0).round() / 100.0);
+                group
+            }).collect();
+            response.other = GeoOther { visits: other_visits, unique_ips: other_ips };
+        }
+        Err(e) => {
+            response.message = Some(format!("获取地理分布失败: {}", e));
+        }
+    }
+
+    response
+}
+
 // 访问者统计API
 #[allow(dead_code)]
-pub async fn get_visitor_stats_handler() -> axum::Json<VisitorStatsResponse> {
-    let total_visits = db::get_total_visits().unwrap_or(0);
-    let unique_ips = db::get_unique_ip_count().unwrap_or(0);
+pub async fn get_visitor_stats_handler(State(storage): State<SharedStorage>) -> axum::Json<VisitorStatsResponse> {
+    let (total_visits, unique_ips) = db::run(move || {
+        (storage.total_visits().unwrap_or(0), storage.unique_visitors().unwrap_or(0))
+    }).await;
     
     axum::Json(VisitorStatsResponse {
         total_visits,
@@ -57,21 +269,30 @@ pub struct VisitorStatsResponse {
     unique_ips: usize,
 }
 
-// 保存访问者统计
-pub fn save_stats() {
-    info!("数据库已保存访问者统计数据");
+// 保存访问者统计：写入存储中累计的访问计数，失败的部分留待下次写入
+pub fn save_stats(storage: &dyn Storage) {
+    match storage.flush_visits() {
+        Ok((0, _)) => {}
+        Ok((visits, visitors)) => {
+            info!("数据库已保存访问者统计数据: {}次访问，{}个访客", visits, visitors);
+            status::publish_visitor_stats(storage);
+        }
+        Err(e) => info!("保存访问者统计数据失败: {}", e),
+    }
 }
 
 // 获取单个IP访问次数API
 #[allow(dead_code)]
 pub async fn get_current_ip_visits(
+    State(storage): State<SharedStorage>,
     req: Request<axum::body::Body>
 ) -> axum::Json<CurrentIpVisitResponse> {
     // 获取客户端IP
     let ip = extract_ip_from_request(&req);
     
     // 获取IP访问次数
-    let visit_count = db::get_ip_visit_count(&ip).unwrap_or(0);
+    let key_ip = ip.clone();
+    let visit_count = db::run(move || storage.visitor_visits(&key_ip).unwrap_or(0)).await;
     
     axum::Json(CurrentIpVisitResponse {
         ip,
@@ -79,16 +300,19 @@ pub async fn get_current_ip_visits(
     })
 }
 
-// 获取IP详细访问信息API
-#[allow(dead_code)]
-pub async fn get_ip_visit_detail_handler(
-    req: Request<axum::body::Body>
+// 获取访客自己的访问记录，返回按隐私设置存储的标识
+pub async fn get_own_visit_record(
+    State(storage): State<SharedStorage>,
+    ClientIp(ip): ClientIp
 ) -> axum::Json<IpVisitDetailResponse> {
-    // 获取客户端IP
-    let ip = extract_ip_from_request(&req);
-    
     // 获取IP详细信息
-    match db::get_ip_visit_detail(&ip) {
+    let (ip, detail) = db::run(move || {
+        let ip = privacy::visitor_key(&ip);
+        let detail = storage.visit_detail(&ip);
+        (ip, detail)
+    }).await;
+    
+    match detail {
         Ok(Some(detail)) => {
             axum::Json(IpVisitDetailResponse {
                 success: true,
@@ -137,19 +361,154 @@ pub async fn get_ip_visit_detail_handler(
     }
 }
 
+// 删除访客自己的访问记录
+pub async fn delete_own_visit_record(
+    State(storage): State<SharedStorage>,
+    ClientIp(ip): ClientIp
+) -> axum::Json<serde_json::Value> {
+    let (success, message) = db::run(move || {
+        match storage.delete_visitor(&privacy::visitor_key(&ip)) {
+            Ok(true) => {
+                info!("访客已删除自己的访问记录");
+                status::publish_visitor_stats(&*storage);
+                (true, "访问记录已删除".to_string())
+            }
+            Ok(false) => (false, "IP记录不存在".to_string()),
+            Err(e) => (false, format!("删除访问记录失败: {}", e)),
+        }
+    }).await;
+    
+    axum::Json(serde_json::json!({
+        "success": success,
+        "message": message,
+    }))
+}
+
 // 从请求中提取IP
 fn extract_ip_from_request(req: &Request<axum::body::Body>) -> String {
-    if let Some(x_forwarded_for) = req.headers().get("x-forwarded-for") {
-        if let Ok(ip_str) = x_forwarded_for.to_str() {
-            ip_str.split(',').next().unwrap_or("unknown").trim().to_string()
+    let peer = req.extensions().get::<ConnectInfo<PeerAddr>>().map(|info| &info.0);
+    client_ip::resolve(peer, req.headers())
+}
+
+// 记录一次访问，地理位置用原始IP解析，存储时按隐私设置转换
+// 访问统计每秒最多推送一次，间隔内的后续变化由定时保存后的推送补上
+pub fn record_visit(storage: &SharedStorage, ip: &str) {
+    let geo = geoip::lookup(ip).unwrap_or_default();
+    let now = now_secs();
+    storage.record_visit(&privacy::visitor_key(ip), geo, now);
+    
+    if LAST_STATS_PUSH.swap(now, Ordering::Relaxed) != now {
+        let storage = storage.clone();
+        tokio::spawn(db::run(move || status::publish_visitor_stats(&*storage)));
+    }
+}
+
+// 判断是否为爬虫，返回匹配的User-Agent关键字
+pub fn classify_bot(user_agent: Option<&str>) -> Option<String> {
+    let user_agent = match user_agent.map(str::trim) {
+        Some(user_agent) if !user_agent.is_empty() => user_agent.to_lowercase(),
+        _ => return Some("empty".to_string()),
+    };
+    
+    let extra = &get_config().visitor_tracking.bot_user_agents;
+    BOT_USER_AGENTS.iter()
+        .map(|pattern| pattern.to_string())
+        .chain(extra.iter().map(|pattern| pattern.to_lowercase()))
+        .find(|pattern| !pattern.is_empty() && user_agent.contains(pattern.as_str()))
+}
+
+// 读取访客Cookie，不存在时生成新的访客Cookie并返回None
+// 新生成的标识不可能出现过，不需要参与去重，也避免不带Cookie的客户端不断写入去重表
+fn visitor_cookie(cookies: &Cookies) -> Option<String> {
+    let cookie_name = get_config().visitor_tracking.cookie_name.clone();
+    if let Some(cookie) = cookies.get(&cookie_name) {
+        return Some(cookie.value().to_string());
+    }
+    
+    let mut cookie = Cookie::new(cookie_name, Uuid::new_v4().simple().to_string());
+    cookie.set_path("/");
+    cookie.set_http_only(true);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(time::Duration::days(365));
+    cookies.add(cookie);
+    
+    None
+}
+
+// 判断是否为新的访问：IP和访客Cookie在去重窗口内都没有出现过
+// 每次请求都会刷新最后出现时间，持续浏览的访客不会被重复计数
+fn is_new_visit(ip: &str, visitor_id: Option<&str>) -> bool {
+    let window = get_config().visitor_tracking.dedupe_window_seconds;
+    if window == 0 {
+        return true;
+    }
+    
+    let mut keys = vec![format!("ip:{}", ip)];
+    if let Some(visitor_id) = visitor_id {
+        keys.push(format!("cookie:{}", visitor_id));
+    }
+    
+    match RECENT_VISITS.lock() {
+        Ok(mut recent) => remember_visit(&mut recent, keys, now_secs(), window),
+        Err(_) => true,
+    }
+}
+
+// 记录访问并返回是否为新的访问，去重表已满时先清理过期记录，仍然满时不再加入新的键
+fn remember_visit(recent: &mut HashMap<String, u64>, keys: Vec<String>, now: u64, window: u64) -> bool {
+    let seen = keys.iter().any(|key| {
+        recent.get(key).is_some_and(|last_seen| now.saturating_sub(*last_seen) < window)
+    });
+    
+    if recent.len() >= MAX_RECENT_VISITS {
+        recent.retain(|_, last_seen| now.saturating_sub(*last_seen) < window);
+    }
+    for key in keys {
+        if recent.len() < MAX_RECENT_VISITS || recent.contains_key(&key) {
+            recent.insert(key, now);
+        }
+    }
+    
+    !seen
+}
+
+// 清理超出去重窗口的访问记录
+fn prune_recent_visits() {
+    let window = get_config().visitor_tracking.dedupe_window_seconds;
+    let now = now_secs();
+    if let Ok(mut recent) = RECENT_VISITS.lock() {
+        recent.retain(|_, last_seen| now.saturating_sub(*last_seen) < window);
+    }
+}
+
+// 首页访问统计中间件，由服务端根据连接地址记录访问
+// 爬虫单独计数，去重窗口内的重复访问不计入
+pub async fn track_visit(
+    State(storage): State<SharedStorage>,
+    req: Request<axum::body::Body>,
+    next: Next
+) -> Response {
+    if req.method() == Method::GET {
+        let user_agent = req.headers().get(USER_AGENT).and_then(|value| value.to_str().ok());
+        
+        if let Some(pattern) = classify_bot(user_agent) {
+            let storage = storage.clone();
+            let result = db::run(move || storage.record_bot_visit(&pattern, now_secs())).await;
+            if let Err(e) = result {
+                info!("记录爬虫访问失败: {}", e);
+            }
         } else {
-            "unknown".to_string()
+            let ip = extract_ip_from_request(&req);
+            let visitor_id = req.extensions().get::<Cookies>().and_then(visitor_cookie);
+            
+            // 无法确定IP的访问（如经Unix套接字且没有转发头）不计入
+            if ip != client_ip::UNKNOWN_IP && is_new_visit(&ip, visitor_id.as_deref()) {
+                record_visit(&storage, &ip);
+            }
         }
-    } else if let Some(socket_addr) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
-        socket_addr.0.ip().to_string()
-    } else {
-        "unknown".to_string()
     }
+    
+    next.run(req).await
 }
 
 #[derive(Serialize)]
@@ -173,84 +532,60 @@ pub struct IpVisitDetailResponse {
     message: Option<String>,
 }
 
-// 创建一个新的端点，让客户端上报自己的IP
-pub async fn report_visitor_ip(req: axum::Json<VisitorReportRequest>) -> axum::Json<VisitorReportResponse> {
-    let ip = req.ip.clone();
-    
-    // 验证IP格式
-    if !is_valid_ip(&ip) {
-        return axum::Json(VisitorReportResponse {
-            success: false,
-            message: "无效的IP地址格式".to_string(),
-            visits: 0,
-        });
-    }
+// 访问信息补充端点：只能为调用者自己的访问记录补充地理位置信息，不计入访问次数
+pub async fn report_visitor_ip(
+    storage: SharedStorage,
+    ip: String,
+    req: axum::Json<VisitorReportRequest>
+) -> axum::Json<VisitorReportResponse> {
+    db::run(move || update_visitor_geo(&*storage, &privacy::visitor_key(&ip), &req)).await
+}
+
+// 补充访问记录的地理位置信息，ip为按隐私设置转换后的标识
+fn update_visitor_geo(storage: &dyn Storage, ip: &str, req: &VisitorReportRequest) -> axum::Json<VisitorReportResponse> {
+    let geo = GeoLocation {
+        continent_code: req.continent_code.clone(),
+        continent_name: req.continent_name.clone(),
+        country_code: req.country_code.clone(),
+        country_name: req.country_name.clone(),
+        state_prov: req.state_prov.clone(),
+        city: req.city.clone(),
+        latitude: None,
+        longitude: None,
+    };
     
-    // 更新访问统计
-    match db::increment_total_visits() {
-        Ok(_) => {
-            // 增加IP访问计数并更新地理位置信息
-            match db::increment_ip_visit(
-                &ip,
-                req.continent_code.as_deref(),
-                req.continent_name.as_deref(),
-                req.country_code.as_deref(),
-                req.country_name.as_deref(),
-                req.state_prov.as_deref(),
-                req.city.as_deref()
-            ) {
-                Ok(visit_count) => {
-                    axum::Json(VisitorReportResponse {
-                        success: true,
-                        message: "访问已记录".to_string(),
-                        visits: visit_count,
-                    })
-                },
-                Err(e) => {
-                    axum::Json(VisitorReportResponse {
-                        success: false,
-                        message: format!("更新IP访问次数失败: {}", e),
-                        visits: 0,
-                    })
-                }
-            }
+    match storage.update_visit_geo(ip, &geo) {
+        Ok(true) => {
+            axum::Json(VisitorReportResponse {
+                success: true,
+                message: "访问信息已更新".to_string(),
+                ip: ip.to_string(),
+                visits: storage.visitor_visits(ip).unwrap_or(0),
+            })
+        },
+        Ok(false) => {
+            axum::Json(VisitorReportResponse {
+                success: false,
+                message: "未找到访问记录".to_string(),
+                ip: ip.to_string(),
+                visits: 0,
+            })
         },
         Err(e) => {
             axum::Json(VisitorReportResponse {
                 success: false,
-                message: format!("更新总访问次数失败: {}", e),
+                message: format!("更新访问信息失败: {}", e),
+                ip: ip.to_string(),
                 visits: 0,
             })
         }
     }
 }
 
-// 验证IP地址格式
-fn is_valid_ip(ip: &str) -> bool {
-    // IPv4格式验证 (简单实现)
-    if let Some(captures) = regex::Regex::new(r"^(\d{1,3})\.(\d{1,3})\.(\d{1,3})\.(\d{1,3})$").ok().and_then(|re| re.captures(ip)) {
-        for i in 1..=4 {
-            if let Some(octet) = captures.get(i) {
-                if let Ok(num) = octet.as_str().parse::<u32>() {
-                    if num > 255 {
-                        return false;
-                    }
-                } else {
-                    return false;
-                }
-            }
-        }
-        return true;
-    }
-    
-    // IPv6格式验证 (简化实现)
-    regex::Regex::new(r"^([0-9a-fA-F]{1,4}:){7}[0-9a-fA-F]{1,4}$").ok()
-        .map_or(false, |re| re.is_match(ip))
-}
-
 #[derive(Deserialize)]
 pub struct VisitorReportRequest {
-    ip: String,
+    #[allow(dead_code)]
+    ip: Option<String>, // 兼容旧客户端，服务端不再使用
     continent_code: Option<String>,
     continent_name: Option<String>,
     country_code: Option<String>,
@@ -263,5 +598,70 @@ pub struct VisitorReportRequest {
 pub struct VisitorReportResponse {
     pub success: bool,
     pub message: String,
+    pub ip: String,
     pub visits: u64,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(ip: &str, visitor_id: Option<&str>) -> Vec<String> {
+        let mut keys = vec![format!("ip:{}", ip)];
+        if let Some(visitor_id) = visitor_id {
+            keys.push(format!("cookie:{}", visitor_id));
+        }
+        keys
+    }
+
+    #[test]
+    fn dedupes_visits_within_window() {
+        let mut recent = HashMap::new();
+        assert!(remember_visit(&mut recent, keys("192.0.2.1", None), 1000, 60));
+        assert!(!remember_visit(&mut recent, keys("192.0.2.1", Some("a")), 1030, 60));
+        // 换了IP但Cookie相同
+        assert!(!remember_visit(&mut recent, keys("192.0.2.2", Some("a")), 1060, 60));
+        // 超出窗口后重新计数
+        assert!(remember_visit(&mut recent, keys("192.0.2.1", None), 1200, 60));
+    }
+
+    #[test]
+    fn caps_recent_visits() {
+        let mut recent = HashMap::new();
+        for i in 0..MAX_RECENT_VISITS {
+            recent.insert(format!("ip:{}", i), 1000);
+        }
+
+        // 表已满且都在窗口内，新的访问仍然计数但不再加入
+        assert!(remember_visit(&mut recent, keys("192.0.2.1", Some("a")), 1010, 60));
+        assert_eq!(recent.len(), MAX_RECENT_VISITS);
+        assert!(remember_visit(&mut recent, keys("192.0.2.1", None), 1020, 60));
+
+        // 已有的键仍会刷新
+        assert!(!remember_visit(&mut recent, keys("0", None), 1030, 60));
+        assert_eq!(recent["ip:0"], 1030);
+
+        // 过期记录被清理后可以加入新的键
+        assert!(remember_visit(&mut recent, keys("192.0.2.1", None), 1080, 60));
+        assert_eq!(recent.len(), 2);
+        assert!(!remember_visit(&mut recent, keys("192.0.2.1", None), 1090, 60));
+    }
+
+    #[tokio::test]
+    async fn caps_visit_history_points() {
+        let _config = crate::config::test_support::set_test_config(|config| {
+            config.visitor_history.hour_retention_days = u64::MAX;
+            config.visitor_history.day_retention_days = u64::MAX;
+        }).await;
+        let storage = crate::storage::MemoryStorage::new();
+
+        for granularity in ["hour", "day"] {
+            let response = get_visit_history(&storage, &VisitorHistoryQuery {
+                granularity: Some(granularity.to_string()),
+                range: Some("99999999d".to_string()),
+            });
+            assert!(response.success);
+            assert_eq!(response.points.len() as u64, MAX_HISTORY_POINTS);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::SystemTime;
+use rimplog::info;
+use rusqlite::{Connection, OpenFlags};
+use tokio::time::{interval_at, Duration, Instant};
+use crate::api::visitor;
+use crate::config::get_config;
+use crate::storage::{SharedStorage, Storage};
+use crate::{db, migrations};
+
+// 定时备份任务状态
+static BACKUP_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// 备份文件信息
+pub struct BackupFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+}
+
+// 备份文件名前缀，取数据库文件名，例如 app.db 的备份为 app-20240501-020000.db
+fn backup_prefix() -> String {
+    let stem = Path::new(&get_config().database.path)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("app")
+        .to_string();
+    format!("{}-", stem)
+}
+
+// 列出备份目录中的备份文件，按时间从旧到新排序
+pub fn list_backups() -> Vec<BackupFile> {
+    let entries = match fs::read_dir(&get_config().database.backup_dir) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let prefix = backup_prefix();
+    let mut backups: Vec<BackupFile> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry.file_name().to_str()
+                .is_some_and(|name| name.starts_with(&prefix) && name.ends_with(".db"))
+        })
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            BackupFile {
+                path: entry.path(),
+                size: metadata.as_ref().map(|metadata| metadata.len()).unwrap_or(0),
+                modified: metadata.and_then(|metadata| metadata.modified().ok()),
+            }
+        })
+        .collect();
+
+    // 文件名中的时间戳保证按名称排序即按时间排序
+    backups.sort_by(|a, b| a.path.cmp(&b.path));
+    backups
+}
+
+// 删除超出保留数量的旧备份
+fn prune_backups() {
+    let keep = get_config().database.backup_keep.max(1);
+    let backups = list_backups();
+    let excess = backups.len().saturating_sub(keep);
+
+    for backup in backups.iter().take(excess) {
+        match fs::remove_file(&backup.path) {
+            Ok(()) => info!("已删除旧备份: {}", backup.path.display()),
+            Err(e) => info!("删除旧备份失败: {}: {}", backup.path.display(), e),
+        }
+    }
+}
+
+// 立即创建一次在线备份，返回备份文件路径
+pub fn create_backup(storage: &dyn Storage) -> Result<PathBuf, String> {
+    let dir = &get_config().database.backup_dir;
+    fs::create_dir_all(dir).map_err(|e| format!("无法创建备份目录: {}: {}", dir, e))?;
+
+    // 先写入存储中累计的访问计数
+    visitor::save_stats(storage);
+
+    let name = format!("{}{}.db", backup_prefix(), chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    let path = Path::new(dir).join(name);
+    if path.exists() {
+        return Err(format!("备份文件已存在: {}", path.display()));
+    }
+
+    let target = path.to_str().ok_or("备份路径包含无效字符")?;
+    db::backup_into(target).map_err(|e| format!("备份数据库失败: {}", e))?;
+    info!("数据库已备份到: {}", path.display());
+
+    prune_backups();
+    Ok(path)
+}
+
+// 启动定时备份任务，按最近一次备份的时间计算首次备份时间，避免频繁重启时一直不备份
+pub fn start_backup_scheduler(storage: SharedStorage) {
+    let database_config = &get_config().database;
+    if database_config.backup_interval_hours == 0 {
+        info!("数据库定时备份已禁用");
+        return;
+    }
+
+    // 防止重复启动
+    if BACKUP_RUNNING.swap(true, Ordering::SeqCst) {
+        info!("定时备份任务已在运行中");
+        return;
+    }
+
+    let period = Duration::from_secs(database_config.backup_interval_hours * 3600);
+    let since_last = list_backups()
+        .last()
+        .and_then(|backup| backup.modified)
+        .and_then(|modified| modified.elapsed().ok());
+    let delay = match since_last {
+        Some(elapsed) => period.saturating_sub(elapsed),
+        None => Duration::ZERO,
+    };
+
+    info!(
+        "启动数据库定时备份，间隔：{}小时，保留{}份，备份目录：{}",
+        database_config.backup_interval_hours, database_config.backup_keep, database_config.backup_dir
+    );
+
+    tokio::spawn(async move {
+        let mut interval_timer = interval_at(Instant::now() + delay, period);
+
+        loop {
+            interval_timer.tick().await;
+            let storage = storage.clone();
+            if let Err(e) = db::run(move || create_backup(&*storage)).await {
+                info!("定时备份失败: {}", e);
+            }
+        }
+    });
+}
+
+// 检查备份文件是有效的数据库，且结构版本不高于程序支持的版本
+fn verify_backup(path: &Path) -> Result<(), String> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .map_err(|e| format!("无法打开备份文件: {}", e))?;
+
+    let integrity: String = conn.query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .map_err(|e| format!("备份文件不是有效的数据库: {}", e))?;
+    if integrity != "ok" {
+        return Err(format!("备份文件已损坏: {}", integrity));
+    }
+
+    let version = migrations::current_version(&conn)
+        .map_err(|e| format!("无法读取备份文件的结构版本: {}", e))?;
+    if version > migrations::latest_version() {
+        return Err(format!(
+            "备份文件的结构版本 {} 高于程序支持的版本 {}",
+            version,
+            migrations::latest_version()
+        ));
+    }
+
+    Ok(())
+}
+
+// 从备份文件恢复数据库，必须在服务器停止时执行
+// 恢复前当前数据库另存为 <数据库路径>.before-restore，旧版本的备份在下次启动时自动迁移
+pub fn restore_backup(backup: &str) -> Result<(), String> {
+    let backup_path = Path::new(backup);
+    if !backup_path.is_file() {
+        return Err(format!("备份文件不存在: {}", backup));
+    }
+    verify_backup(backup_path)?;
+
+    let db_path = &get_config().database.path;
+    if let Some(parent) = Path::new(db_path).parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("无法创建数据库目录: {}", e))?;
+    }
+
+    if Path::new(db_path).exists() {
+        let previous = format!("{}.before-restore", db_path);
+        if Path::new(&previous).exists() {
+            fs::remove_file(&previous).map_err(|e| format!("无法删除旧的恢复前备份: {}: {}", previous, e))?;
+        }
+
+        // 关闭连接时WAL中的内容会合并到另存的文件中
+        let conn = Connection::open(db_path).map_err(|e| format!("无法打开当前数据库: {}", e))?;
+        conn.execute("VACUUM INTO ?", [&previous])
+            .map_err(|e| format!("保存当前数据库失败: {}", e))?;
+        drop(conn);
+        info!("当前数据库已另存为: {}", previous);
+    }
+
+    // 删除旧数据库遗留的WAL文件，否则会被应用到恢复后的数据库上
+    for suffix in ["-wal", "-shm"] {
+        let path = format!("{}{}", db_path, suffix);
+        if Path::new(&path).exists() {
+            fs::remove_file(&path).map_err(|e| format!("无法删除 {}: {}", path, e))?;
+        }
+    }
+
+    fs::copy(backup_path, db_path).map_err(|e| format!("复制备份文件失败: {}", e))?;
+    info!("数据库已从 {} 恢复到 {}", backup, db_path);
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use std::io::Write;
+use std::path::PathBuf;
+use clap::{Parser, Subcommand};
+use crate::api::transfer::{self, TransferFormat};
+use crate::backup;
+use crate::config::{self, get_config, init_config};
+use crate::db::init_db;
+use crate::listen::ListenAddr;
+use crate::log::init_log;
+use crate::storage::SqliteStorage;
+
+// 命令行参数，全局参数可以放在子命令前后
+#[derive(Parser)]
+#[command(
+    name = "lycrex-home",
+    version,
+    about = "LycreX 主页服务器",
+    after_help = "环境变量 LYCREX__<段>__<键> 覆盖配置文件中的值，例如 LYCREX__SERVER__PORT=8080"
+)]
+pub struct Cli {
+    /// 配置文件路径，不存在时写入默认配置
+    #[arg(long, global = true, value_name = "文件", default_value = "config.toml")]
+    pub config: PathBuf,
+
+    /// 数据目录，配置中数据库、备份目录和GeoIP数据库的相对路径以此目录为基准
+    #[arg(long, global = true, value_name = "目录")]
+    pub data_dir: Option<PathBuf>,
+
+    /// 监听地址，可以指定多次，支持 127.0.0.1:8080、[::]:8080 和 unix:/run/lycrex.sock，覆盖配置中的 server.listen
+    #[arg(long, global = true, value_name = "地址", value_parser = ListenAddr::parse)]
+    pub bind: Vec<ListenAddr>,
+
+    /// 日志级别
+    #[arg(
+        long,
+        global = true,
+        value_name = "级别",
+        default_value = "info",
+        value_parser = ["error", "warn", "info", "debug", "trace"]
+    )]
+    pub log_level: String,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// 启动服务器（默认）
+    Serve,
+    /// 检查配置文件和环境变量覆盖后的配置是否有效
+    CheckConfig,
+    /// 输出默认配置
+    PrintDefaultConfig,
+    /// 导出访客统计，未指定文件时输出到标准输出
+    ExportVisitors {
+        /// 导出文件
+        file: Option<String>,
+        /// 导出格式，默认按文件扩展名判断
+        #[arg(short, long, value_parser = parse_format)]
+        format: Option<TransferFormat>,
+    },
+    /// 导入并合并访客统计，相同IP的访问次数相加
+    ImportVisitors {
+        /// 导入文件
+        file: String,
+        /// 导入格式，默认按文件扩展名判断
+        #[arg(short, long, value_parser = parse_format)]
+        format: Option<TransferFormat>,
+    },
+    /// 立即备份数据库到配置的备份目录
+    Backup,
+    /// 从备份恢复数据库，需要先停止服务器
+    Restore {
+        /// 备份文件
+        backup: String,
+    },
+}
+
+fn parse_format(value: &str) -> Result<TransferFormat, String> {
+    TransferFormat::parse(value).ok_or_else(|| format!("未知的格式: {}，可用: json, csv", value))
+}
+
+// 初始化子命令需要的配置和数据库，输出到标准输出时不启用日志
+fn init_storage(log_level: Option<&str>) -> Result<(), String> {
+    if let Some(level) = log_level {
+        init_log(level);
+    }
+    init_config().map_err(|e| format!("配置初始化失败: {}", e))?;
+    init_db(&get_config().database.path).map_err(|e| format!("数据库初始化失败: {}", e))?;
+    Ok(())
+}
+
+// 检查配置
+fn check_config() -> Result<(), String> {
+    config::check_config()?;
+    println!("配置有效: {}", config::config_path().display());
+    Ok(())
+}
+
+// 输出默认配置
+fn print_default_config() -> Result<(), String> {
+    print!("{}", config::default_config_toml()?);
+    Ok(())
+}
+
+// 导出访客统计
+fn export_visitors(file: Option<String>, format: Option<TransferFormat>, log_level: &str) -> Result<(), String> {
+    let format = format.unwrap_or_else(|| file.as_deref().map(TransferFormat::from_path).unwrap_or(TransferFormat::Json));
+
+    init_storage(file.as_ref().map(|_| log_level))?;
+    let content = transfer::export_visitors(&SqliteStorage::new(), format)?;
+
+    match file {
+        Some(path) => {
+            std::fs::write(&path, content).map_err(|e| format!("写入文件失败: {}: {}", path, e))?;
+            eprintln!("访客统计已导出到: {}", path);
+        }
+        None => {
+            std::io::stdout().write_all(content.as_bytes())
+                .map_err(|e| format!("写入标准输出失败: {}", e))?;
+        }
+    }
+
+    Ok(())
+}
+
+// 导入并合并访客统计
+fn import_visitors(path: String, format: Option<TransferFormat>, log_level: &str) -> Result<(), String> {
+    let format = format.unwrap_or_else(|| TransferFormat::from_path(&path));
+
+    let content = std::fs::read_to_string(&path).map_err(|e| format!("读取文件失败: {}: {}", path, e))?;
+
+    init_storage(Some(log_level))?;
+    let summary = transfer::import_visitors(&SqliteStorage::new(), format, &content)?;
+    eprintln!(
+        "导入完成: 新增{}个IP，合并{}个IP，总访问次数增加{}",
+        summary.inserted, summary.merged, summary.total_visits_added
+    );
+
+    Ok(())
+}
+
+// 立即备份数据库
+fn backup_now(log_level: &str) -> Result<(), String> {
+    init_storage(Some(log_level))?;
+    let path = backup::create_backup(&SqliteStorage::new())?;
+    eprintln!("数据库已备份到: {}", path.display());
+
+    Ok(())
+}
+
+// 从备份恢复数据库，不打开数据库连接，避免恢复前后的文件被占用
+fn restore(path: String, log_level: &str) -> Result<(), String> {
+    init_log(log_level);
+    init_config().map_err(|e| format!("配置初始化失败: {}", e))?;
+    backup::restore_backup(&path)?;
+    eprintln!("数据库已从 {} 恢复到 {}", path, get_config().database.path);
+
+    Ok(())
+}
+
+// 执行服务器以外的子命令，返回进程退出码
+pub fn run(command: Command, log_level: &str) -> i32 {
+    let result = match command {
+        Command::Serve => return 0,
+        Command::CheckConfig => check_config(),
+        Command::PrintDefaultConfig => print_default_config(),
+        Command::ExportVisitors { file, format } => export_visitors(file, format, log_level),
+        Command::ImportVisitors { file, format } => import_visitors(file, format, log_level),
+        Command::Backup => backup_now(log_level),
+        Command::Restore { backup } => restore(backup, log_level),
+    };
+
+    match result {
+        Ok(()) => 0,
+        Err(message) => {
+            eprintln!("{}", message);
+            1
+        }
+    }
+}
@@ -1,13 +1,17 @@
 use serde::{Deserialize, Serialize};
 use std::sync::{OnceLock, Arc, Mutex};
 use std::fs;
-use std::path::Path;
-use std::time::{SystemTime, Duration};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH, Duration};
 use notify::{Watcher, RecursiveMode, Event, EventKind};
 use rimplog::info;
-use crate::api::status::update_server_status;
+use tokio::sync::watch;
+use crate::listen::{parse_socket_mode, ListenAddr};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct ServerConfig {
     pub name: String,
     pub status: String,
@@ -17,9 +21,31 @@ pub struct ServerConfig {
     pub port: u16,
     pub show_visitor_stats: VisitorStatsConfig,
     pub auth: AuthConfig,
+    #[serde(default = "default_trusted_proxies")]
+    pub trusted_proxies: Vec<String>,
+    // 启用后只接受来自 trusted_proxies 的TCP连接
+    #[serde(default)]
+    pub proxy_protocol: bool,
+    // 监听地址列表，支持 IP:端口、[IPv6]:端口 和 unix:/路径，为空时监听 0.0.0.0:port，修改后需要重启
+    #[serde(default)]
+    pub listen: Vec<String>,
+    // Unix套接字文件的权限（八进制）
+    #[serde(default = "default_unix_socket_mode")]
+    pub unix_socket_mode: String,
+}
+
+impl ServerConfig {
+    // 解析监听地址列表
+    pub fn listen_addrs(&self) -> Result<Vec<ListenAddr>, String> {
+        if self.listen.is_empty() {
+            return Ok(vec![ListenAddr::Tcp(SocketAddr::from(([0, 0, 0, 0], self.port)))]);
+        }
+        self.listen.iter().map(|value| ListenAddr::parse(value)).collect()
+    }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct VisitorStatsConfig {
     pub enabled: bool,
     pub show_total_visits: bool,
@@ -27,20 +53,220 @@ pub struct VisitorStatsConfig {
     pub show_personal_visits: bool,
 }
 
+// 密码可以写成 ${环境变量}，或用 password_file 从文件读取
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct AuthConfig {
+    #[serde(default)]
     pub password: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_file: Option<String>,
     pub token_expiration_seconds: u64,
 }
 
+// 客户端密钥可以写成 ${环境变量}，或用 client_secret_file 从文件读取
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct OAuthConfig {
     pub auth_server_url: String,
     pub client_id: String,
+    #[serde(default)]
     pub client_secret: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret_file: Option<String>,
     pub redirect_uri: String,
 }
 
+// 系统指标采样配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    pub sample_interval_seconds: u64,
+    pub minute_retention_hours: u64,
+    pub hour_retention_days: u64,
+}
+
+// 访问时间序列配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct VisitorHistoryConfig {
+    pub hour_retention_days: u64,
+    pub day_retention_days: u64,
+}
+
+// 访问计数配置：去重时间窗口和额外的爬虫User-Agent关键字
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct VisitorTrackingConfig {
+    pub dedupe_window_seconds: u64,
+    pub cookie_name: String,
+    pub bot_user_agents: Vec<String>,
+}
+
+// 访客IP的存储方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum IpMode {
+    Full,
+    Hash,
+    Truncate,
+}
+
+// 过期访客记录的处理方式
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RetentionAction {
+    Delete,
+    Anonymize,
+}
+
+// 访客隐私配置，retention_days 为0表示永久保留，geo_min_count 为地理分布的最小独立IP数
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct PrivacyConfig {
+    pub ip_mode: IpMode,
+    pub salt_rotation_hours: u64,
+    pub retention_days: u64,
+    pub retention_action: RetentionAction,
+    pub geo_min_count: u64,
+}
+
+// 对等节点配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct NodeConfig {
+    pub name: String,
+    pub url: String,
+    #[serde(default = "default_true")]
+    pub show_latency: bool,
+}
+
+// 节点状态轮询配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct NodePollerConfig {
+    pub interval_seconds: u64,
+    pub timeout_ms: u64,
+}
+
+// 状态推送(SSE)配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct StatusStreamConfig {
+    pub push_interval_seconds: u64,
+    pub heartbeat_seconds: u64,
+}
+
+// 访问统计和备忘录的存储方式，memory 不写入数据库，重启后丢失
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    Sqlite,
+    Memory,
+}
+
+// 数据库位置和定时备份配置，backup_interval_hours为0时不定时备份
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub path: String,
+    #[serde(default)]
+    pub storage: StorageBackend,
+    pub backup_dir: String,
+    pub backup_interval_hours: u64,
+    pub backup_keep: usize,
+}
+
+// 本地GeoIP数据库配置
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub struct GeoIpConfig {
+    pub enabled: bool,
+    pub database_path: String,
+    pub language: String,
+}
+
+// 服务监控类型
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum MonitorKind {
+    Http,
+    Tcp,
+}
+
+// 服务监控配置，HTTP监控的target为URL，TCP监控的target为 host:port
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+pub struct MonitorConfig {
+    pub name: String,
+    pub kind: MonitorKind,
+    pub target: String,
+    #[serde(default = "default_monitor_interval")]
+    pub interval_seconds: u64,
+    #[serde(default = "default_monitor_timeout")]
+    pub timeout_ms: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_status: Option<u16>,
+}
+
+fn default_monitor_interval() -> u64 {
+    60
+}
+
+fn default_monitor_timeout() -> u64 {
+    5000
+}
+
+// 默认监控 enter 命令中的各项服务
+fn default_monitors() -> Vec<MonitorConfig> {
+    [
+        ("git", "https://git.lycrex.com"),
+        ("tv", "https://tv.lycrex.com"),
+        ("pan", "https://pan.lycrex.com"),
+    ]
+    .iter()
+    .map(|(name, url)| MonitorConfig {
+        name: name.to_string(),
+        kind: MonitorKind::Http,
+        target: url.to_string(),
+        interval_seconds: default_monitor_interval(),
+        timeout_ms: default_monitor_timeout(),
+        expected_status: None,
+    })
+    .collect()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+// 默认只允许所属用户和组（如nginx所在组）连接Unix套接字
+fn default_unix_socket_mode() -> String {
+    "660".to_string()
+}
+
+// 默认只信任本机反向代理
+fn default_trusted_proxies() -> Vec<String> {
+    vec!["127.0.0.1/32".to_string(), "::1/128".to_string()]
+}
+
+// 默认的对等节点列表
+fn default_nodes() -> Vec<NodeConfig> {
+    [
+        ("Hong Kong Server", "https://hk.lycrex.com/api/status"),
+        ("Los Angeles Server", "https://us.lycrex.com/api/status"),
+        ("Japan Server", "https://jp.lycrex.com/api/status"),
+        ("Git & Lfs Server", "https://git.lycrex.com"),
+    ]
+    .iter()
+    .map(|(name, url)| NodeConfig {
+        name: name.to_string(),
+        url: url.to_string(),
+        show_latency: true,
+    })
+    .collect()
+}
+
 impl Default for VisitorStatsConfig {
     fn default() -> Self {
         Self {
@@ -52,21 +278,106 @@ impl Default for VisitorStatsConfig {
     }
 }
 
+// 密钥默认引用环境变量，默认配置中不包含密钥本身
 impl Default for AuthConfig {
     fn default() -> Self {
         Self {
-            password: "123".to_string(),
+            password: "${AUTH_PASSWORD}".to_string(),
+            password_file: None,
             token_expiration_seconds: 3600,
         }
     }
 }
 
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            sample_interval_seconds: 60,
+            minute_retention_hours: 24,
+            hour_retention_days: 30,
+        }
+    }
+}
+
+impl Default for VisitorHistoryConfig {
+    fn default() -> Self {
+        Self {
+            hour_retention_days: 30,
+            day_retention_days: 400,
+        }
+    }
+}
+
+impl Default for VisitorTrackingConfig {
+    fn default() -> Self {
+        Self {
+            dedupe_window_seconds: 1800,
+            cookie_name: "lycrex_visitor".to_string(),
+            bot_user_agents: Vec::new(),
+        }
+    }
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            ip_mode: IpMode::Full,
+            salt_rotation_hours: 24,
+            retention_days: 0,
+            retention_action: RetentionAction::Anonymize,
+            geo_min_count: 3,
+        }
+    }
+}
+
+impl Default for NodePollerConfig {
+    fn default() -> Self {
+        Self {
+            interval_seconds: 60,
+            timeout_ms: 5000,
+        }
+    }
+}
+
+impl Default for StatusStreamConfig {
+    fn default() -> Self {
+        Self {
+            push_interval_seconds: 5,
+            heartbeat_seconds: 15,
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            path: "data/app.db".to_string(),
+            storage: StorageBackend::Sqlite,
+            backup_dir: "data/backups".to_string(),
+            backup_interval_hours: 24,
+            backup_keep: 7,
+        }
+    }
+}
+
+impl Default for GeoIpConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            database_path: "data/GeoLite2-City.mmdb".to_string(),
+            language: "en".to_string(),
+        }
+    }
+}
+
 impl Default for OAuthConfig {
     fn default() -> Self {
         Self {
             auth_server_url: get_env_or_default("AUTH_SERVER_URL", "http://127.0.0.1:8080"),
             client_id: get_env_or_default("CLIENT_ID", "profile-client"),
-            client_secret: get_env_or_default("CLIENT_SECRET", "profile-secret"),
+            client_secret: "${CLIENT_SECRET}".to_string(),
+            client_secret_file: None,
             redirect_uri: get_env_or_default("REDIRECT_URI", "http://localhost:3000/auth/callback"),
         }
     }
@@ -83,14 +394,39 @@ impl Default for ServerConfig {
             port: 1111,
             show_visitor_stats: VisitorStatsConfig::default(),
             auth: AuthConfig::default(),
+            trusted_proxies: default_trusted_proxies(),
+            proxy_protocol: false,
+            listen: Vec::new(),
+            unix_socket_mode: default_unix_socket_mode(),
         }
     }
 }
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub struct Config {
     pub server: ServerConfig,
     pub oauth: OAuthConfig,
+    #[serde(default)]
+    pub database: DatabaseConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub visitor_tracking: VisitorTrackingConfig,
+    #[serde(default)]
+    pub visitor_history: VisitorHistoryConfig,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub node_poller: NodePollerConfig,
+    #[serde(default)]
+    pub status_stream: StatusStreamConfig,
+    #[serde(default)]
+    pub geoip: GeoIpConfig,
+    #[serde(default = "default_nodes")]
+    pub nodes: Vec<NodeConfig>,
+    #[serde(default = "default_monitors")]
+    pub monitors: Vec<MonitorConfig>,
 }
 
 impl Default for Config {
@@ -98,192 +434,662 @@ impl Default for Config {
         Self {
             server: ServerConfig::default(),
             oauth: OAuthConfig::default(),
+            database: DatabaseConfig::default(),
+            metrics: MetricsConfig::default(),
+            visitor_tracking: VisitorTrackingConfig::default(),
+            visitor_history: VisitorHistoryConfig::default(),
+            privacy: PrivacyConfig::default(),
+            node_poller: NodePollerConfig::default(),
+            status_stream: StatusStreamConfig::default(),
+            geoip: GeoIpConfig::default(),
+            nodes: default_nodes(),
+            monitors: default_monitors(),
+        }
+    }
+}
+
+// 令牌过期时间的允许范围
+const MIN_TOKEN_EXPIRATION_SECONDS: u64 = 60;
+const MAX_TOKEN_EXPIRATION_SECONDS: u64 = 30 * 86400;
+
+impl Config {
+    // 检查配置取值，有问题时返回所有问题的说明
+    pub fn validate(&self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        
+        if self.server.port == 0 {
+            errors.push("server.port 必须在 1-65535 之间".to_string());
+        }
+        
+        if let Err(e) = self.server.listen_addrs() {
+            errors.push(format!("server.listen {}", e));
+        }
+        
+        if let Err(e) = parse_socket_mode(&self.server.unix_socket_mode) {
+            errors.push(format!("server.unix_socket_mode {}", e));
+        }
+        
+        let expiration = self.server.auth.token_expiration_seconds;
+        if !(MIN_TOKEN_EXPIRATION_SECONDS..=MAX_TOKEN_EXPIRATION_SECONDS).contains(&expiration) {
+            errors.push(format!(
+                "server.auth.token_expiration_seconds 必须在 {}-{} 之间，当前为 {}",
+                MIN_TOKEN_EXPIRATION_SECONDS, MAX_TOKEN_EXPIRATION_SECONDS, expiration
+            ));
+        }
+        
+        for (field, value) in [
+            ("server.auth.password", &self.server.auth.password),
+            ("oauth.client_id", &self.oauth.client_id),
+            ("oauth.client_secret", &self.oauth.client_secret),
+        ] {
+            if value.trim().is_empty() {
+                errors.push(format!("{} 不能为空", field));
+            }
+        }
+        
+        for (field, value) in [
+            ("oauth.auth_server_url", &self.oauth.auth_server_url),
+            ("oauth.redirect_uri", &self.oauth.redirect_uri),
+        ] {
+            if let Err(e) = check_http_url(value) {
+                errors.push(format!("{} {}: {}", field, e, value));
+            }
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
         }
     }
 }
 
-static CONFIG: OnceLock<Config> = OnceLock::new();
+// 检查是否为带主机名的http或https地址
+fn check_http_url(value: &str) -> Result<(), String> {
+    let url = reqwest::Url::parse(value).map_err(|e| format!("不是有效的URL（{}）", e))?;
+    if !matches!(url.scheme(), "http" | "https") || !url.has_host() {
+        return Err("必须是http或https地址".to_string());
+    }
+    Ok(())
+}
 
-// 添加一个全局变量来记录上次配置文件更新时间
-static LAST_CONFIG_UPDATE: OnceLock<Mutex<SystemTime>> = OnceLock::new();
+// 最近一次重新加载失败的信息
+#[derive(Debug, Serialize, Clone)]
+pub struct ReloadError {
+    pub message: String,
+    pub timestamp: u64,
+}
+
+// 环境变量覆盖的前缀和分隔符，例如 LYCREX__SERVER__PORT 覆盖 server.port
+// 值按字符串读取，反序列化时再按字段类型转换，避免 "007" 这样的密钥被当作数字
+const ENV_PREFIX: &str = "LYCREX";
+const ENV_SEPARATOR: &str = "__";
+
+// 命令行指定的配置文件和数据目录
+struct ConfigSource {
+    path: PathBuf,
+    data_dir: Option<PathBuf>,
+}
+
+static CONFIG_SOURCE: OnceLock<ConfigSource> = OnceLock::new();
+
+// 当前配置，重新加载时整体替换，订阅者通过watch通道收到变更通知
+static CONFIG: OnceLock<watch::Sender<Arc<Config>>> = OnceLock::new();
+
+// 当前配置的加载时间
+static CONFIG_LOADED_AT: AtomicU64 = AtomicU64::new(0);
+
+// 最近一次重新加载失败的原因，重新加载成功后清除
+static LAST_RELOAD_ERROR: Mutex<Option<ReloadError>> = Mutex::new(None);
+
+// 配置文件监听器和当前监听的目录
+struct ConfigWatcher {
+    watcher: notify::RecommendedWatcher,
+    dirs: Vec<PathBuf>,
+}
+
+static CONFIG_WATCHER: OnceLock<Mutex<ConfigWatcher>> = OnceLock::new();
+
+// 当前监听的文件，其中任一文件变更时重新加载配置
+static WATCHED_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+// 收到文件变更通知后等待的时间
+const RELOAD_DELAY: Duration = Duration::from_millis(200);
+
+// 是否已有等待执行的重新加载
+static RELOAD_PENDING: AtomicBool = AtomicBool::new(false);
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
 
 // 获取环境变量，如果不存在则使用默认值
 pub fn get_env_or_default(key: &str, default: &str) -> String {
     std::env::var(key).unwrap_or_else(|_| default.to_string())
 }
 
-pub fn init_config() -> Result<(), config::ConfigError> {
-    let config_path = "config.toml";
-    
-    // 尝试读取配置文件
-    let result = config::Config::builder()
-        .add_source(config::File::with_name("config"))
-        .build()
-        .and_then(|settings| settings.try_deserialize());
+// 设置配置文件路径和数据目录，需要在初始化配置前调用
+pub fn set_config_source(path: PathBuf, data_dir: Option<PathBuf>) {
+    if CONFIG_SOURCE.set(ConfigSource { path, data_dir }).is_err() {
+        info!("配置来源已设置，忽略重复设置");
+    }
+}
 
-    match result {
-        Ok(config) => {
-            CONFIG.set(config).expect("配置已初始化");
-            Ok(())
+fn config_source() -> &'static ConfigSource {
+    CONFIG_SOURCE.get_or_init(|| ConfigSource {
+        path: PathBuf::from("config.toml"),
+        data_dir: None,
+    })
+}
+
+// 当前使用的配置文件路径
+pub fn config_path() -> &'static Path {
+    &config_source().path
+}
+
+// 配置中的相对数据路径以数据目录为基准
+fn resolve_data_paths(config: &mut Config, data_dir: &Path) {
+    for path in [
+        &mut config.database.path,
+        &mut config.database.backup_dir,
+        &mut config.geoip.database_path,
+    ] {
+        if Path::new(path.as_str()).is_relative() {
+            *path = data_dir.join(path.as_str()).to_string_lossy().into_owned();
         }
-        Err(_) => {
-            // 如果配置文件不存在或读取失败，创建默认配置
-            if !Path::new(config_path).exists() {
-                let default_config = Config::default();
-                let toml = toml::to_string_pretty(&default_config)
-                    .expect("无法序列化默认配置");
-                fs::write(config_path, toml)
-                    .expect("无法写入默认配置文件");
-                
-                CONFIG.set(default_config).expect("配置已初始化");
+    }
+}
+
+// 将值中的 ${NAME} 替换为环境变量NAME的值，$${ 表示字面的 ${
+fn expand_env(value: &str) -> Result<String, String> {
+    let mut result = String::new();
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let end = rest[start..].find('}').ok_or("中的 ${ 缺少对应的 }")? + start;
+        let name = &rest[start + 2..end];
+        let env = std::env::var(name).map_err(|_| format!("引用的环境变量 {} 未设置", name))?;
+        result.push_str(&env);
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+// 解析密钥字段，指定了文件时读取文件内容，否则展开值中的环境变量
+// 错误信息中不包含密钥本身
+fn resolve_secret(field: &str, value: &mut String, file: Option<&str>) -> Result<(), String> {
+    match file {
+        Some(file) => {
+            if !value.is_empty() {
+                return Err(format!("{} 和 {}_file 不能同时设置", field, field));
             }
-            Ok(())
+            let file = expand_env(file).map_err(|e| format!("{}_file {}", field, e))?;
+            let content = fs::read_to_string(&file)
+                .map_err(|e| format!("无法读取 {}_file: {}: {}", field, file, e))?;
+            *value = content.trim_end_matches(['\r', '\n']).to_string();
+        }
+        None => {
+            *value = expand_env(value).map_err(|e| format!("{} {}", field, e))?;
         }
     }
+    Ok(())
 }
 
-pub fn get_config() -> &'static Config {
-    CONFIG.get().expect("配置未初始化")
+// 解析所有密钥字段，每次加载配置时重新读取
+fn resolve_secrets(config: &mut Config) -> Result<(), String> {
+    let auth = &mut config.server.auth;
+    resolve_secret("server.auth.password", &mut auth.password, auth.password_file.as_deref())?;
+    let oauth = &mut config.oauth;
+    resolve_secret("oauth.client_secret", &mut oauth.client_secret, oauth.client_secret_file.as_deref())?;
+    Ok(())
 }
 
-pub fn get_server_config() -> &'static ServerConfig {
-    &get_config().server
+// 读取配置文件并叠加环境变量覆盖，然后校验
+fn load_config(source: &ConfigSource) -> Result<Config, String> {
+    let mut config: Config = config::Config::builder()
+        .add_source(config::File::from(source.path.as_path()).format(config::FileFormat::Toml))
+        .add_source(
+            config::Environment::with_prefix(ENV_PREFIX)
+                .prefix_separator(ENV_SEPARATOR)
+                .separator(ENV_SEPARATOR)
+        )
+        .build()
+        .and_then(|settings| settings.try_deserialize())
+        .map_err(|e| format!("解析配置文件失败: {}: {}", source.path.display(), e))?;
+    
+    if let Some(data_dir) = &source.data_dir {
+        resolve_data_paths(&mut config, data_dir);
+    }
+    resolve_secrets(&mut config).map_err(|e| format!("配置无效: {}", e))?;
+    
+    config.validate().map_err(|e| format!("配置无效: {}", e))?;
+    Ok(config)
 }
 
-pub fn get_oauth_config() -> &'static OAuthConfig {
-    &get_config().oauth
+// 检查配置文件是否有效，不修改当前配置
+pub fn check_config() -> Result<(), String> {
+    if !config_path().exists() {
+        return Err(format!("配置文件不存在: {}", config_path().display()));
+    }
+    load_config(config_source()).map(|_| ())
 }
 
-// 获取认证配置，从status模块中获取最新配置
-pub fn get_auth_config() -> Option<crate::api::status::AuthConfig> {
-    // 直接从status模块访问SERVER_CONFIG
-    if let Some(mutex) = crate::api::status::SERVER_CONFIG.get() {
-        if let Ok(config) = mutex.lock() {
-            return Some(config.auth.clone());
+// 默认配置文件的说明
+const DEFAULT_CONFIG_HEADER: &str = "\
+# 密钥从环境变量 AUTH_PASSWORD 和 CLIENT_SECRET 读取，也可以改用 password_file 和 client_secret_file
+# 值中的 ${NAME} 会替换为环境变量NAME的值，字面的 ${ 写作 $${
+
+";
+
+// 默认配置的TOML文本
+pub fn default_config_toml() -> Result<String, String> {
+    let content = toml::to_string_pretty(&Config::default()).map_err(|e| format!("无法序列化默认配置: {}", e))?;
+    Ok(format!("{}{}", DEFAULT_CONFIG_HEADER, content))
+}
+
+pub fn init_config() -> Result<(), String> {
+    let config_path = config_path();
+    
+    // 配置文件不存在时写入默认配置
+    if !config_path.exists() {
+        if let Some(parent) = config_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("无法创建配置目录: {}: {}", parent.display(), e))?;
         }
+        fs::write(config_path, default_config_toml()?)
+            .map_err(|e| format!("无法写入默认配置文件: {}: {}", config_path.display(), e))?;
+        info!("已写入默认配置文件: {}", config_path.display());
     }
     
-    // 如果无法从status模块获取，则回退到静态配置
-    Some(crate::api::status::AuthConfig {
-        password: get_server_config().auth.password.clone(),
-        token_expiration_seconds: get_server_config().auth.token_expiration_seconds,
-    })
+    set_config(load_config(config_source())?);
+    Ok(())
+}
+
+// 替换当前配置并通知所有订阅者
+fn set_config(config: Config) {
+    let config = Arc::new(config);
+    CONFIG.get_or_init(|| watch::channel(config.clone()).0).send_replace(config);
+    CONFIG_LOADED_AT.store(now_secs(), Ordering::Relaxed);
+}
+
+// 当前配置的加载时间
+pub fn config_loaded_at() -> u64 {
+    CONFIG_LOADED_AT.load(Ordering::Relaxed)
+}
+
+// 获取最近一次重新加载失败的信息
+pub fn last_reload_error() -> Option<ReloadError> {
+    LAST_RELOAD_ERROR.lock().ok().and_then(|error| error.clone())
+}
+
+// 获取当前配置的快照，同一次处理中多次读取时应保存快照，避免前后读到不同版本
+pub fn get_config() -> Arc<Config> {
+    CONFIG.get().expect("配置未初始化").borrow().clone()
+}
+
+// 订阅配置变更
+pub fn subscribe_config() -> watch::Receiver<Arc<Config>> {
+    CONFIG.get().expect("配置未初始化").subscribe()
+}
+
+pub fn get_server_config() -> ServerConfig {
+    get_config().server.clone()
+}
+
+pub fn get_oauth_config() -> OAuthConfig {
+    get_config().oauth.clone()
+}
+
+pub fn get_auth_config() -> AuthConfig {
+    get_config().server.auth.clone()
 }
 
 // 开始监听配置文件变更
 pub fn start_config_watcher() -> notify::Result<()> {
-    let config_path = "config.toml";
-    
-    // 初始化上次更新时间
-    LAST_CONFIG_UPDATE.get_or_init(|| Mutex::new(SystemTime::now()));
-    
-    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+    let watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
         match res {
             Ok(event) => {
-                if matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
-                    let now = SystemTime::now();
-                    let mut should_reload = false;
-                    
-                    if let Some(last_update_mutex) = LAST_CONFIG_UPDATE.get() {
-                        if let Ok(mut last_update) = last_update_mutex.lock() {
-                            // 设置冷却时间为100毫秒
-                            let cooldown = Duration::from_millis(100);
-                            
-                            if now.duration_since(*last_update).unwrap_or_else(|_| Duration::from_secs(0)) > cooldown {
-                                *last_update = now;
-                                should_reload = true;
-                            }
-                        }
-                    }
-                    
-                    if should_reload {
+                let is_watched_file = WATCHED_FILES.lock()
+                    .is_ok_and(|files| event.paths.iter().any(|path| files.contains(path)));
+                // 等待一段时间后再重新加载，合并同一次保存产生的多个通知，
+                // 避免在文件被截断、尚未写入新内容时读取
+                if is_watched_file
+                    && matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_))
+                    && !RELOAD_PENDING.swap(true, Ordering::SeqCst)
+                {
+                    std::thread::spawn(|| {
+                        std::thread::sleep(RELOAD_DELAY);
+                        RELOAD_PENDING.store(false, Ordering::SeqCst);
                         info!("检测到配置文件变更，正在重新加载...");
                         
                         // 重新加载配置文件
-                        match reload_config() {
+                        match reload_config(config_source()) {
                             Ok(new_config) => {
-                                // 转换并更新服务器配置
-                                let server_config = crate::api::status::ServerConfig {
-                                    name: new_config.server.name.clone(),
-                                    status: new_config.server.status.clone(),
-                                    message: new_config.server.message.clone(),
-                                    title: new_config.server.title.clone(),
-                                    subtitle: new_config.server.subtitle.clone(),
-                                    show_visitor_stats: crate::api::status::VisitorStatsConfig {
-                                        enabled: new_config.server.show_visitor_stats.enabled,
-                                        show_total_visits: new_config.server.show_visitor_stats.show_total_visits,
-                                        show_unique_ips: new_config.server.show_visitor_stats.show_unique_ips,
-                                        show_personal_visits: new_config.server.show_visitor_stats.show_personal_visits,
-                                    },
-                                    auth: crate::api::status::AuthConfig {
-                                        password: new_config.server.auth.password.clone(),
-                                        token_expiration_seconds: new_config.server.auth.token_expiration_seconds,
-                                    },
-                                };
-                                
-                                // 更新服务器状态
-                                update_server_status(server_config);
-                                
                                 // 记录OAuth配置更新信息
                                 info!("OAuth配置已更新: auth_server_url={}, client_id={}", 
                                       new_config.oauth.auth_server_url,
                                       new_config.oauth.client_id);
                             },
                             Err(e) => {
-                                info!("重新加载配置失败: {}", e);
+                                info!("重新加载配置失败，继续使用之前的配置: {}", e);
                             }
                         }
-                    }
+                    });
                 }
             },
             Err(e) => info!("监听配置文件错误: {:?}", e),
         }
     })?;
     
-    // 开始监听配置文件
-    watcher.watch(Path::new(config_path), RecursiveMode::NonRecursive)?;
+    if CONFIG_WATCHER.set(Mutex::new(ConfigWatcher { watcher, dirs: Vec::new() })).is_err() {
+        info!("配置文件监听已在运行中");
+        return Ok(());
+    }
+    rearm_config_watcher()?;
     
-    static WATCHER: OnceLock<Arc<Mutex<Box<dyn notify::Watcher + Send>>>> = OnceLock::new();
-    WATCHER.set(Arc::new(Mutex::new(Box::new(watcher)))).unwrap_or(());
+    // 重新加载后按新配置更新监听，密钥文件的路径可能已变化
+    // 不能在通知回调中修改监听，回调运行在监听器自身的线程上
+    let mut config_receiver = subscribe_config();
+    tokio::spawn(async move {
+        while config_receiver.changed().await.is_ok() {
+            config_receiver.borrow_and_update();
+            if let Ok(Err(e)) = tokio::task::spawn_blocking(rearm_config_watcher).await {
+                info!("更新配置文件监听失败: {}", e);
+            }
+        }
+    });
     
     info!("配置文件监听已启动");
     Ok(())
 }
 
-// 重新加载配置文件
-fn reload_config() -> Result<Config, config::ConfigError> {
-    // 解析配置文件
-    let new_config = config::Config::builder()
-        .add_source(config::File::with_name("config"))
-        .build()
-        .and_then(|settings| settings.try_deserialize::<Config>())?;
+// 需要监听的文件：配置文件和配置中引用的密钥文件，转换为绝对路径以便与通知中的路径比较
+fn watched_files(config_path: &Path, config: &Config) -> Vec<PathBuf> {
+    let secret_files = [&config.server.auth.password_file, &config.oauth.client_secret_file];
+    std::iter::once(config_path.to_path_buf())
+        .chain(secret_files.into_iter().flatten().filter_map(|file| expand_env(file).ok()).map(PathBuf::from))
+        .filter_map(|path| std::path::absolute(path).ok())
+        .collect()
+}
 
-    // 记录关键配置信息
-    info!("重新加载配置: 系统密码={}, 令牌过期时间={}秒", 
-          new_config.server.auth.password, 
-          new_config.server.auth.token_expiration_seconds);
-
-    // 转换并更新服务器配置
-    let server_config = crate::api::status::ServerConfig {
-        name: new_config.server.name.clone(),
-        status: new_config.server.status.clone(),
-        message: new_config.server.message.clone(),
-        title: new_config.server.title.clone(),
-        subtitle: new_config.server.subtitle.clone(),
-        show_visitor_stats: crate::api::status::VisitorStatsConfig {
-            enabled: new_config.server.show_visitor_stats.enabled,
-            show_total_visits: new_config.server.show_visitor_stats.show_total_visits,
-            show_unique_ips: new_config.server.show_visitor_stats.show_unique_ips,
-            show_personal_visits: new_config.server.show_visitor_stats.show_personal_visits,
-        },
-        auth: crate::api::status::AuthConfig {
-            password: new_config.server.auth.password.clone(),
-            token_expiration_seconds: new_config.server.auth.token_expiration_seconds,
-        },
+// 按当前配置更新监听的目录
+// 监听所在目录而不是文件本身，编辑器保存时替换文件也能收到通知
+fn rearm_config_watcher() -> notify::Result<()> {
+    let Some(config_watcher) = CONFIG_WATCHER.get() else {
+        return Ok(());
     };
+    let files = watched_files(config_path(), &get_config());
+    let mut dirs: Vec<PathBuf> = files.iter().filter_map(|file| file.parent().map(Path::to_path_buf)).collect();
+    dirs.sort();
+    dirs.dedup();
     
-    // 更新服务器状态
-    update_server_status(server_config);
+    let mut result = Ok(());
+    if let Ok(mut config_watcher) = config_watcher.lock() {
+        let ConfigWatcher { watcher, dirs: watched } = &mut *config_watcher;
+        for dir in watched.iter().filter(|dir| !dirs.contains(dir)) {
+            let _ = watcher.unwatch(dir);
+        }
+        watched.retain(|dir| dirs.contains(dir));
+        for dir in dirs {
+            if watched.contains(&dir) {
+                continue;
+            }
+            match watcher.watch(&dir, RecursiveMode::NonRecursive) {
+                Ok(()) => watched.push(dir),
+                Err(e) => result = Err(e),
+            }
+        }
+    }
+    
+    // 在修改监听之后单独更新，修改监听时会等待回调线程，而回调需要读取监听的文件
+    if let Ok(mut watched_files) = WATCHED_FILES.lock() {
+        *watched_files = files;
+    }
+    result
+}
+
+// 重新加载配置文件，失败时保留当前配置并记录原因
+fn reload_config(source: &ConfigSource) -> Result<Arc<Config>, String> {
+    let new_config = match load_config(source) {
+        Ok(config) => config,
+        Err(e) => {
+            if let Ok(mut error) = LAST_RELOAD_ERROR.lock() {
+                *error = Some(ReloadError { message: e.clone(), timestamp: now_secs() });
+            }
+            return Err(e);
+        }
+    };
+
+    // 记录关键配置信息
+    info!("重新加载配置: 令牌过期时间={}秒", new_config.server.auth.token_expiration_seconds);
+
+    // 更新受信任代理
+    crate::api::client_ip::set_trusted_proxies(&new_config.server.trusted_proxies);
+
+    // 替换当前配置，订阅者随后收到变更通知
+    set_config(new_config);
+    if let Ok(mut error) = LAST_RELOAD_ERROR.lock() {
+        *error = None;
+    }
+
+    Ok(get_config())
+}
+
+// 测试用：替换全局配置，修改全局配置的测试通过持有返回的锁串行执行
+#[cfg(test)]
+pub mod test_support {
+    use super::{set_config, Config};
+    use tokio::sync::{Mutex, MutexGuard};
+
+    static CONFIG_LOCK: Mutex<()> = Mutex::const_new(());
+
+    pub async fn set_test_config(update: impl FnOnce(&mut Config)) -> MutexGuard<'static, ()> {
+        let guard = CONFIG_LOCK.lock().await;
+        let mut config = Config::default();
+        update(&mut config);
+        set_config(config);
+        guard
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::test_support::set_test_config;
+
+    // 校验配置，返回错误信息
+    fn validate_with(update: impl FnOnce(&mut Config)) -> String {
+        let mut config = Config::default();
+        update(&mut config);
+        config.validate().unwrap_err()
+    }
+
+    // 测试用的临时配置文件
+    fn temp_source(name: &str, content: &str) -> ConfigSource {
+        let dir = std::env::temp_dir().join(format!("lycrex-home-config-{}-{}", std::process::id(), name));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        fs::write(&path, content).unwrap();
+        ConfigSource { path, data_dir: None }
+    }
+
+    // 密钥直接写在配置中的完整配置
+    fn config_with_secrets() -> Config {
+        let mut config = Config::default();
+        config.server.auth.password = "password".to_string();
+        config.oauth.client_secret = "secret".to_string();
+        config
+    }
+
+    // 只包含密钥的配置文件内容
+    const SECRETS_TOML: &str = "[server.auth]\npassword = \"password\"\n\n[oauth]\nclient_secret = \"secret\"\n";
+
+    #[test]
+    fn default_config_is_valid() {
+        assert_eq!(Config::default().validate(), Ok(()));
+    }
+
+    #[test]
+    fn rejects_port_zero() {
+        assert!(validate_with(|config| config.server.port = 0).contains("server.port"));
+    }
+
+    #[test]
+    fn rejects_invalid_listen_and_socket_mode() {
+        let error = validate_with(|config| config.server.listen = vec!["localhost".to_string()]);
+        assert!(error.contains("server.listen"));
+        assert!(validate_with(|config| config.server.listen = vec!["unix:".to_string()]).contains("server.listen"));
 
-    Ok(new_config)
-} 
\ No newline at end of file
+        assert!(validate_with(|config| config.server.unix_socket_mode = "999".to_string()).contains("unix_socket_mode"));
+        assert!(validate_with(|config| config.server.unix_socket_mode = "1777".to_string()).contains("unix_socket_mode"));
+    }
+
+    #[test]
+    fn checks_token_expiration_bounds() {
+        for expiration in [MIN_TOKEN_EXPIRATION_SECONDS, MAX_TOKEN_EXPIRATION_SECONDS] {
+            let mut config = Config::default();
+            config.server.auth.token_expiration_seconds = expiration;
+            assert_eq!(config.validate(), Ok(()));
+        }
+        for expiration in [0, MIN_TOKEN_EXPIRATION_SECONDS - 1, MAX_TOKEN_EXPIRATION_SECONDS + 1] {
+            let error = validate_with(|config| config.server.auth.token_expiration_seconds = expiration);
+            assert!(error.contains("token_expiration_seconds"));
+        }
+    }
+
+    #[test]
+    fn rejects_empty_secrets() {
+        assert!(validate_with(|config| config.server.auth.password = " ".to_string()).contains("server.auth.password"));
+        assert!(validate_with(|config| config.oauth.client_id = String::new()).contains("oauth.client_id"));
+        assert!(validate_with(|config| config.oauth.client_secret = String::new()).contains("oauth.client_secret"));
+    }
+
+    #[test]
+    fn rejects_bad_urls() {
+        let error = validate_with(|config| config.oauth.auth_server_url = "not a url".to_string());
+        assert!(error.contains("oauth.auth_server_url") && error.contains("不是有效的URL"));
+
+        let error = validate_with(|config| config.oauth.redirect_uri = "ftp://example.com/callback".to_string());
+        assert!(error.contains("oauth.redirect_uri") && error.contains("必须是http或https地址"));
+    }
+
+    #[test]
+    fn reports_all_errors() {
+        let error = validate_with(|config| {
+            config.server.port = 0;
+            config.oauth.client_id = String::new();
+        });
+        assert_eq!(error.split("; ").count(), 2);
+    }
+
+    #[test]
+    fn watches_config_and_secret_files() {
+        let mut config = Config::default();
+        assert_eq!(watched_files(Path::new("/etc/lycrex/config.toml"), &config), vec![PathBuf::from("/etc/lycrex/config.toml")]);
+
+        config.server.auth.password_file = Some("/run/secrets/password".to_string());
+        config.oauth.client_secret_file = Some("secrets/client_secret".to_string());
+        let files = watched_files(Path::new("config.toml"), &config);
+        let cwd = std::env::current_dir().unwrap();
+        assert_eq!(files, vec![
+            cwd.join("config.toml"),
+            PathBuf::from("/run/secrets/password"),
+            cwd.join("secrets/client_secret"),
+        ]);
+    }
+
+    #[test]
+    fn fills_missing_fields_with_defaults() {
+        let content = format!("{}\n[server]\nport = 2222\n\n[database]\npath = \"custom.db\"\n", SECRETS_TOML);
+        let source = temp_source("partial", &content);
+        let config = load_config(&source).unwrap();
+        assert_eq!(config.server.port, 2222);
+        assert_eq!(config.server.title, ServerConfig::default().title);
+        assert_eq!(config.database.path, "custom.db");
+        assert_eq!(config.database.backup_dir, DatabaseConfig::default().backup_dir);
+        assert_eq!(config.database.backup_keep, DatabaseConfig::default().backup_keep);
+        assert_eq!(config.metrics.sample_interval_seconds, MetricsConfig::default().sample_interval_seconds);
+
+        fs::remove_dir_all(source.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn env_overrides_keep_field_types() {
+        let source = temp_source("env", SECRETS_TOML);
+        let vars = [
+            ("LYCREX__GEOIP__DATABASE_PATH", "/tmp/geo.mmdb"),
+            ("LYCREX__GEOIP__ENABLED", "false"),
+            ("LYCREX__STATUS_STREAM__HEARTBEAT_SECONDS", "30"),
+            ("LYCREX__OAUTH__CLIENT_SECRET", "007"),
+        ];
+        for (key, value) in vars {
+            std::env::set_var(key, value);
+        }
+        let config = load_config(&source);
+        for (key, _) in vars {
+            std::env::remove_var(key);
+        }
+
+        let config = config.unwrap();
+        assert_eq!(config.geoip.database_path, "/tmp/geo.mmdb");
+        assert!(!config.geoip.enabled);
+        assert_eq!(config.geoip.language, GeoIpConfig::default().language);
+        assert_eq!(config.status_stream.heartbeat_seconds, 30);
+        assert_eq!(config.oauth.client_secret, "007");
+
+        fs::remove_dir_all(source.path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn default_config_references_secrets() {
+        let content = default_config_toml().unwrap();
+        assert!(content.contains("password = \"${AUTH_PASSWORD}\""));
+        assert!(content.contains("client_secret = \"${CLIENT_SECRET}\""));
+        assert!(toml::from_str::<Config>(&content).is_ok());
+    }
+
+    #[test]
+    fn expands_env_references() {
+        std::env::set_var("LYCREX_TEST_SECRET", "s3cret");
+        assert_eq!(expand_env("a${LYCREX_TEST_SECRET}b").unwrap(), "as3cretb");
+        assert_eq!(expand_env("$${LYCREX_TEST_SECRET}").unwrap(), "${LYCREX_TEST_SECRET}");
+        assert_eq!(expand_env("$$${LYCREX_TEST_SECRET}").unwrap(), "$${LYCREX_TEST_SECRET}");
+        assert_eq!(expand_env("cost $5").unwrap(), "cost $5");
+        assert!(expand_env("${LYCREX_TEST_MISSING}").unwrap_err().contains("LYCREX_TEST_MISSING"));
+        assert!(expand_env("${LYCREX_TEST_SECRET").is_err());
+        std::env::remove_var("LYCREX_TEST_SECRET");
+    }
+
+    #[tokio::test]
+    async fn failed_reload_keeps_current_config() {
+        let _guard = set_test_config(|config| config.server.port = 4321).await;
+
+        let mut config = config_with_secrets();
+        config.server.port = 0;
+        let source = temp_source("reload", &toml::to_string_pretty(&config).unwrap());
+        let error = reload_config(&source).unwrap_err();
+        assert!(error.contains("配置无效") && error.contains("server.port"));
+        assert_eq!(get_config().server.port, 4321);
+        assert_eq!(last_reload_error().map(|error| error.message), Some(error));
+
+        // 修复配置后重新加载成功并清除错误
+        let mut config = config_with_secrets();
+        config.server.port = 4322;
+        fs::write(&source.path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(reload_config(&source).unwrap().server.port, 4322);
+        assert_eq!(get_config().server.port, 4322);
+        assert!(last_reload_error().is_none());
+
+        fs::remove_dir_all(source.path.parent().unwrap()).unwrap();
+    }
+}
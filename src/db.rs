@@ -1,8 +1,28 @@
-use std::sync::{Arc, Mutex, OnceLock};
-use rusqlite::{Connection, Result as SqliteResult};
+use std::collections::HashMap;
+use std::sync::{LockResult, Mutex, MutexGuard, OnceLock, TryLockError};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use rusqlite::{Connection, Result as SqliteResult, TransactionBehavior};
 use std::path::Path;
 use rimplog::info;
 use std::time::{SystemTime, UNIX_EPOCH};
+use serde::{Deserialize, Serialize};
+use crate::geoip::GeoLocation;
+use crate::migrations;
+
+// 地理分组统计
+#[derive(Debug, Clone, Serialize)]
+pub struct GeoGroup {
+    pub continent_code: Option<String>,
+    pub continent_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub city: Option<String>,
+    pub visits: u64,
+    pub unique_ips: u64,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
 
 // IP访问记录结构体
 #[derive(Debug, Clone)]
@@ -18,8 +38,114 @@ pub struct IpVisitRecord {
     pub last_visit: u64,
 }
 
-// 数据库连接单例
-static DB_CONN: OnceLock<Arc<Mutex<Connection>>> = OnceLock::new();
+// 访客数据导出记录，包含访客记录的全部列
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IpVisitExport {
+    pub ip: String,
+    pub visit_count: u64,
+    pub continent_code: Option<String>,
+    pub continent_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub state_prov: Option<String>,
+    pub city: Option<String>,
+    pub last_visit: Option<u64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+// 导入结果
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImportSummary {
+    pub inserted: usize,
+    pub merged: usize,
+    pub total_visits_added: u64,
+}
+
+// 待写入的单个访客访问计数
+#[derive(Debug, Clone, Default)]
+pub struct PendingIpVisit {
+    pub visits: u64,
+    pub last_visit: u64,
+    pub geo: GeoLocation,
+}
+
+// 内存中累计、定时批量写入的访问计数
+#[derive(Debug, Clone, Default)]
+pub struct VisitBatch {
+    pub total_visits: u64,
+    pub ips: HashMap<String, PendingIpVisit>,
+    // (小时桶, 访客标识) -> 访问次数
+    pub buckets: HashMap<(u64, String), u64>,
+}
+
+impl VisitBatch {
+    pub fn is_empty(&self) -> bool {
+        self.total_visits == 0 && self.ips.is_empty()
+    }
+
+    // 合并另一批计数，写入失败时用于放回未写入的数据
+    pub fn merge(&mut self, other: VisitBatch) {
+        self.total_visits += other.total_visits;
+        for (ip, visit) in other.ips {
+            let entry = self.ips.entry(ip).or_default();
+            entry.visits += visit.visits;
+            entry.last_visit = entry.last_visit.max(visit.last_visit);
+            // 当前批次的地理位置更新，只补充缺失的字段
+            let geo = &mut entry.geo;
+            geo.continent_code = geo.continent_code.take().or(visit.geo.continent_code);
+            geo.continent_name = geo.continent_name.take().or(visit.geo.continent_name);
+            geo.country_code = geo.country_code.take().or(visit.geo.country_code);
+            geo.country_name = geo.country_name.take().or(visit.geo.country_name);
+            geo.state_prov = geo.state_prov.take().or(visit.geo.state_prov);
+            geo.city = geo.city.take().or(visit.geo.city);
+            geo.latitude = geo.latitude.or(visit.geo.latitude);
+            geo.longitude = geo.longitude.or(visit.geo.longitude);
+        }
+        for (key, visits) in other.buckets {
+            *self.buckets.entry(key).or_insert(0) += visits;
+        }
+    }
+}
+
+// 连接池大小，WAL模式下读操作可以并发，写操作由SQLite串行执行
+const POOL_SIZE: usize = 4;
+
+// 写锁冲突时的等待时间
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 数据库连接池
+pub struct DbPool {
+    conns: Vec<Mutex<Connection>>,
+    next: AtomicUsize,
+}
+
+impl DbPool {
+    // 获取一个空闲连接，全部被占用时等待轮询到的连接
+    pub fn lock(&'static self) -> LockResult<MutexGuard<'static, Connection>> {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+        for offset in 0..self.conns.len() {
+            match self.conns[(start + offset) % self.conns.len()].try_lock() {
+                Ok(guard) => return Ok(guard),
+                Err(TryLockError::Poisoned(e)) => return Err(e),
+                Err(TryLockError::WouldBlock) => continue,
+            }
+        }
+        self.conns[start % self.conns.len()].lock()
+    }
+}
+
+// 数据库连接池单例
+static DB_POOL: OnceLock<DbPool> = OnceLock::new();
+
+// 打开连接并设置WAL模式和忙等待
+fn open_connection(db_path: &str) -> SqliteResult<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.pragma_update(None, "journal_mode", "WAL")?;
+    conn.pragma_update(None, "synchronous", "NORMAL")?;
+    Ok(conn)
+}
 
 // 用户备忘录结构体
 #[derive(Debug, Clone)]
@@ -39,6 +165,42 @@ pub struct PublicNote {
     pub last_updated: u64,
 }
 
+// 服务监控检查记录结构体
+#[derive(Debug, Clone)]
+pub struct MonitorCheck {
+    pub monitor: String,
+    pub timestamp: u64,
+    pub success: bool,
+    pub response_time_ms: Option<u64>,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+}
+
+// 事件/维护公告结构体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Incident {
+    pub id: i64,
+    pub kind: String,
+    pub title: String,
+    pub severity: String,
+    pub affected_services: Vec<String>,
+    pub status: String,
+    pub created_at: u64,
+    pub scheduled_start: Option<u64>,
+    pub scheduled_end: Option<u64>,
+    pub resolved_at: Option<u64>,
+    pub resolution: Option<String>,
+    pub updates: Vec<IncidentUpdate>,
+}
+
+// 事件进展记录结构体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IncidentUpdate {
+    pub timestamp: u64,
+    pub status: String,
+    pub message: String,
+}
+
 // 用户频道设置结构体
 #[derive(Debug, Clone)]
 pub struct UserChannelSetting {
@@ -66,108 +228,52 @@ pub fn init_db(db_path: &str) -> SqliteResult<()> {
     }
     
     // 创建数据库连接
-    let conn = Connection::open(db_path)?;
+    let mut conn = open_connection(db_path)?;
     
-    // 创建访问者统计表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS visitor_stats (
-            id INTEGER PRIMARY KEY,
-            total_visits INTEGER NOT NULL DEFAULT 0
-        )",
-        [],
-    )?;
+    // 升级数据库结构
+    migrations::run_migrations(&mut conn)?;
     
-    // 创建IP访问表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS ip_visits (
-            ip TEXT PRIMARY KEY,
-            visit_count INTEGER NOT NULL DEFAULT 0,
-            continent_code TEXT,
-            continent_name TEXT,
-            country_code TEXT,
-            country_name TEXT,
-            state_prov TEXT,
-            city TEXT,
-            last_visit INTEGER
-        )",
-        [],
-    )?;
-    
-    // 创建用户备忘录表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS user_notes (
-            user_id TEXT PRIMARY KEY,
-            content TEXT NOT NULL,
-            last_updated INTEGER NOT NULL
-        )",
-        [],
-    )?;
-    
-    // 创建公共便利贴表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS public_notes (
-            channel_id INTEGER PRIMARY KEY,
-            content TEXT NOT NULL,
-            last_updated INTEGER NOT NULL
-        )",
-        [],
-    )?;
-    
-    // 创建用户频道设置表
-    conn.execute(
-        "CREATE TABLE IF NOT EXISTS user_channel_settings (
-            user_id TEXT PRIMARY KEY,
-            channel_id INTEGER NOT NULL DEFAULT 0,
-            last_updated INTEGER NOT NULL
-        )",
-        [],
-    )?;
-    
-    // 存储连接
-    DB_CONN.get_or_init(|| Arc::new(Mutex::new(conn)));
+    // 创建连接池
+    let mut conns = vec![Mutex::new(conn)];
+    for _ in 1..POOL_SIZE {
+        conns.push(Mutex::new(open_connection(db_path)?));
+    }
+    DB_POOL.get_or_init(|| DbPool {
+        conns,
+        next: AtomicUsize::new(0),
+    });
     
-    info!("数据库初始化完成: {}", db_path);
+    info!("数据库初始化完成: {} (结构版本 {}，连接数 {})", db_path, migrations::latest_version(), POOL_SIZE);
     
     Ok(())
 }
 
-// 获取数据库连接
-pub fn get_db_conn() -> Arc<Mutex<Connection>> {
-    DB_CONN.get()
-        .expect("数据库未初始化")
-        .clone()
+// 获取数据库连接池
+pub fn get_db_conn() -> &'static DbPool {
+    DB_POOL.get().expect("数据库未初始化")
 }
 
-// 递增总访问次数
-pub fn increment_total_visits() -> SqliteResult<u64> {
+// 在阻塞线程池中执行数据库操作，避免占用异步运行时的工作线程
+pub async fn run<F, T>(task: F) -> T
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(task).await.expect("数据库任务执行失败")
+}
+
+// 在线备份数据库到指定文件，目标文件不能已存在
+pub fn backup_into(path: &str) -> SqliteResult<()> {
     let conn = get_db_conn();
     let conn = conn.lock().expect("无法获取数据库锁");
     
-    // 首先获取当前值
-    let mut stmt = conn.prepare("SELECT total_visits FROM visitor_stats WHERE id = 1")?;
-    let total_visits: Result<u64, _> = stmt.query_row([], |row| row.get(0));
+    conn.execute("VACUUM INTO ?", [path])?;
     
-    let current_total = match total_visits {
-        Ok(count) => count,
-        Err(_) => {
-            // 如果没有记录，创建一个初始记录
-            conn.execute("INSERT INTO visitor_stats (id, total_visits) VALUES (1, 0)", [])?;
-            0
-        }
-    };
-    
-    // 更新总访问次数
-    let new_total = current_total + 1;
-    conn.execute(
-        "UPDATE visitor_stats SET total_visits = ? WHERE id = 1",
-        [new_total],
-    )?;
-    
-    Ok(new_total)
+    Ok(())
 }
 
-// 递增指定IP的访问次数
-pub fn increment_ip_visit(
+// 补充指定IP已有记录的地理位置信息，不改变访问次数，记录不存在时返回false
+pub fn update_ip_geo(
     ip: &str,
     continent_code: Option<&str>,
     continent_name: Option<&str>,
@@ -175,95 +281,32 @@ pub fn increment_ip_visit(
     country_name: Option<&str>,
     state_prov: Option<&str>,
     city: Option<&str>
-) -> SqliteResult<u64> {
+) -> SqliteResult<bool> {
     let conn = get_db_conn();
     let conn = conn.lock().expect("无法获取数据库锁");
     
-    // 获取当前时间戳
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_secs();
-    
-    // 查询是否已存在记录
-    let exists: bool = conn.query_row(
-        "SELECT 1 FROM ip_visits WHERE ip = ?",
-        [ip],
-        |_| Ok(true)
-    ).unwrap_or(false);
-    
-    if exists {
-        // 如果存在记录，更新访问次数和地理位置信息（如果提供）
-        let mut query = String::from(
-            "UPDATE ip_visits SET 
-             visit_count = visit_count + 1,
-             last_visit = ?"
-        );
-        
-        let mut params: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(now)];
-        
-        if let Some(code) = continent_code {
-            query.push_str(", continent_code = ?");
-            params.push(Box::new(code));
-        }
-        
-        if let Some(name) = continent_name {
-            query.push_str(", continent_name = ?");
-            params.push(Box::new(name));
-        }
-        
-        if let Some(code) = country_code {
-            query.push_str(", country_code = ?");
-            params.push(Box::new(code));
-        }
-        
-        if let Some(name) = country_name {
-            query.push_str(", country_name = ?");
-            params.push(Box::new(name));
-        }
-        
-        if let Some(state) = state_prov {
-            query.push_str(", state_prov = ?");
-            params.push(Box::new(state));
-        }
-        
-        if let Some(c) = city {
-            query.push_str(", city = ?");
-            params.push(Box::new(c));
-        }
-        
-        query.push_str(" WHERE ip = ?");
-        params.push(Box::new(ip));
-        
-        let mut stmt = conn.prepare(&query)?;
-        stmt.execute(rusqlite::params_from_iter(params.iter()))?;
-    } else {
-        // 如果不存在记录，插入新记录
-        conn.execute(
-            "INSERT INTO ip_visits 
-             (ip, visit_count, continent_code, continent_name, country_code, country_name, state_prov, city, last_visit) 
-             VALUES (?, 1, ?, ?, ?, ?, ?, ?, ?)",
-            rusqlite::params![
-                ip, 
-                continent_code, 
-                continent_name, 
-                country_code, 
-                country_name, 
-                state_prov, 
-                city,
-                now
-            ],
-        )?;
-    }
-    
-    // 获取更新后的访问次数
-    let visit_count: u64 = conn.query_row(
-        "SELECT visit_count FROM ip_visits WHERE ip = ?",
-        [ip],
-        |row| row.get(0),
+    // 未提供的字段保留原值
+    let changed = conn.execute(
+        "UPDATE ip_visits SET
+         continent_code = COALESCE(?, continent_code),
+         continent_name = COALESCE(?, continent_name),
+         country_code = COALESCE(?, country_code),
+         country_name = COALESCE(?, country_name),
+         state_prov = COALESCE(?, state_prov),
+         city = COALESCE(?, city)
+         WHERE ip = ?",
+        rusqlite::params![
+            continent_code,
+            continent_name,
+            country_code,
+            country_name,
+            state_prov,
+            city,
+            ip
+        ],
     )?;
     
-    Ok(visit_count)
+    Ok(changed > 0)
 }
 
 // 获取总访问次数
@@ -297,6 +340,25 @@ pub fn get_unique_ip_count() -> SqliteResult<usize> {
     Ok(count as usize)
 }
 
+// 统计已有记录的IP数量，每次最多查询500个IP，避免超出SQLite参数数量限制
+pub fn count_known_ips(ips: &[String]) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let mut known = 0;
+    for chunk in ips.chunks(500) {
+        let placeholders = vec!["?"; chunk.len()].join(", ");
+        let count: i64 = conn.query_row(
+            &format!("SELECT COUNT(*) FROM ip_visits WHERE ip IN ({})", placeholders),
+            rusqlite::params_from_iter(chunk),
+            |row| row.get(0),
+        )?;
+        known += count as usize;
+    }
+    
+    Ok(known)
+}
+
 // 获取指定IP的访问次数
 pub fn get_ip_visit_count(ip: &str) -> SqliteResult<u64> {
     let conn = get_db_conn();
@@ -529,4 +591,742 @@ pub fn get_user_channel_setting(user_id: &str) -> SqliteResult<Option<UserChanne
         Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
         Err(e) => Err(e),
     }
-} 
\ No newline at end of file
+} 
+
+// 记录一次系统指标采样，同时累加到分钟和小时两个聚合桶中
+pub fn record_metric_sample(timestamp: u64, cpu: f64, memory: f64) -> SqliteResult<()> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    for (resolution, size) in [("minute", 60), ("hour", 3600)] {
+        let bucket = timestamp - timestamp % size;
+        conn.execute(
+            "INSERT INTO metric_samples (resolution, bucket, cpu_sum, memory_sum, samples)
+             VALUES (?1, ?2, ?3, ?4, 1)
+             ON CONFLICT(resolution, bucket) DO UPDATE SET
+                cpu_sum = cpu_sum + excluded.cpu_sum,
+                memory_sum = memory_sum + excluded.memory_sum,
+                samples = samples + 1",
+            rusqlite::params![resolution, bucket, cpu, memory],
+        )?;
+    }
+    
+    Ok(())
+}
+
+// 清理超出保留期限的指标数据
+pub fn purge_metric_samples(minute_before: u64, hour_before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let minutes = conn.execute(
+        "DELETE FROM metric_samples WHERE resolution = 'minute' AND bucket < ?",
+        [minute_before],
+    )?;
+    let hours = conn.execute(
+        "DELETE FROM metric_samples WHERE resolution = 'hour' AND bucket < ?",
+        [hour_before],
+    )?;
+    
+    Ok(minutes + hours)
+}
+
+// 获取指定分辨率下某项指标的历史平均值 (时间桶, 平均值)
+pub fn get_metric_history(resolution: &str, metric: &str, since: u64) -> SqliteResult<Vec<(u64, f64)>> {
+    // 指标名只允许固定的列，避免拼接任意SQL
+    let column = match metric {
+        "cpu" => "cpu_sum",
+        "memory" => "memory_sum",
+        _ => return Ok(Vec::new()),
+    };
+    
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let mut stmt = conn.prepare(&format!(
+        "SELECT bucket, {} / samples FROM metric_samples
+         WHERE resolution = ? AND bucket >= ? AND samples > 0
+         ORDER BY bucket",
+        column
+    ))?;
+    let rows = stmt.query_map(rusqlite::params![resolution, since], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 获取指定周期的盐值，不存在时保存候选值，并删除之前周期的盐值
+pub fn get_or_create_privacy_salt(period: u64, candidate: &str) -> SqliteResult<String> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "INSERT OR IGNORE INTO privacy_salts (period, salt) VALUES (?, ?)",
+        rusqlite::params![period, candidate],
+    )?;
+    conn.execute("DELETE FROM privacy_salts WHERE period < ?", [period])?;
+    
+    conn.query_row(
+        "SELECT salt FROM privacy_salts WHERE period = ?",
+        [period],
+        |row| row.get(0),
+    )
+}
+
+// 删除单个访客记录，时间序列中的标识匿名化以保留计数
+pub fn delete_ip_visit(ip: &str) -> SqliteResult<bool> {
+    let conn = get_db_conn();
+    let mut conn = conn.lock().expect("无法获取数据库锁");
+    
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let deleted = tx.execute("DELETE FROM ip_visits WHERE ip = ?", [ip])?;
+    tx.execute(
+        "UPDATE visit_buckets SET ip = 'anon:' || rowid WHERE ip = ?",
+        [ip],
+    )?;
+    tx.commit()?;
+    
+    Ok(deleted > 0)
+}
+
+// 删除最后访问早于指定时间的访客记录
+pub fn delete_ip_visits_before(before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute("DELETE FROM ip_visits WHERE last_visit < ?", [before])
+}
+
+// 匿名化最后访问早于指定时间的访客记录：替换标识并清除省份、城市和坐标
+pub fn anonymize_ip_visits_before(before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "UPDATE ip_visits SET ip = 'anon:' || rowid, state_prov = NULL, city = NULL,
+            latitude = NULL, longitude = NULL
+         WHERE last_visit < ? AND ip NOT LIKE 'anon:%'",
+        [before],
+    )
+}
+
+// 匿名化早于指定时间的访问时间序列标识
+pub fn anonymize_visit_buckets_before(before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "UPDATE visit_buckets SET ip = 'anon:' || rowid
+         WHERE bucket < ? AND ip NOT LIKE 'anon:%'",
+        [before],
+    )
+}
+
+// 按地理层级聚合访问记录，只返回独立IP数不少于阈值的分组
+// 返回 (分组, 低于阈值而未列出的访问次数和独立IP数)
+pub fn get_geo_breakdown(level: &str, min_count: u64) -> SqliteResult<(Vec<GeoGroup>, u64, u64)> {
+    // 分组列只允许固定的组合，避免拼接任意SQL
+    let columns = match level {
+        "continent" => "continent_code, continent_name, NULL, NULL, NULL",
+        "country" => "continent_code, continent_name, country_code, country_name, NULL",
+        "city" => "continent_code, continent_name, country_code, country_name, city",
+        _ => return Ok((Vec::new(), 0, 0)),
+    };
+    let group_by = match level {
+        "continent" => "continent_code",
+        "country" => "continent_code, country_code",
+        _ => "continent_code, country_code, city",
+    };
+    
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {}, SUM(visit_count), COUNT(*), AVG(latitude), AVG(longitude)
+         FROM ip_visits
+         GROUP BY {}
+         ORDER BY SUM(visit_count) DESC",
+        columns, group_by
+    ))?;
+    let rows = stmt.query_map([], |row| {
+        Ok(GeoGroup {
+            continent_code: row.get(0)?,
+            continent_name: row.get(1)?,
+            country_code: row.get(2)?,
+            country_name: row.get(3)?,
+            city: row.get(4)?,
+            visits: row.get(5)?,
+            unique_ips: row.get(6)?,
+            latitude: row.get(7)?,
+            longitude: row.get(8)?,
+        })
+    })?;
+    
+    let mut groups = Vec::new();
+    let mut other_visits = 0;
+    let mut other_ips = 0;
+    for row in rows {
+        let group = row?;
+        // 未知位置和人数过少的分组合并到其他
+        if group.unique_ips < min_count || group.continent_code.is_none() {
+            other_visits += group.visits;
+            other_ips += group.unique_ips;
+        } else {
+            groups.push(group);
+        }
+    }
+    
+    Ok((groups, other_visits, other_ips))
+}
+
+// 记录一次页面访问和来源域名
+pub fn record_page_view(path: &str, referrer: Option<&str>, timestamp: u64) -> SqliteResult<()> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let bucket = timestamp - timestamp % 86400;
+    conn.execute(
+        "INSERT INTO page_views (bucket, path, views) VALUES (?1, ?2, 1)
+         ON CONFLICT(bucket, path) DO UPDATE SET views = views + 1",
+        rusqlite::params![bucket, path],
+    )?;
+    if let Some(host) = referrer {
+        conn.execute(
+            "INSERT INTO referrer_visits (bucket, host, visits) VALUES (?1, ?2, 1)
+             ON CONFLICT(bucket, host) DO UPDATE SET visits = visits + 1",
+            rusqlite::params![bucket, host],
+        )?;
+    }
+    
+    Ok(())
+}
+
+// 清理早于指定时间的页面和来源统计
+pub fn purge_page_views(before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let pages = conn.execute("DELETE FROM page_views WHERE bucket < ?", [before])?;
+    let referrers = conn.execute("DELETE FROM referrer_visits WHERE bucket < ?", [before])?;
+    
+    Ok(pages + referrers)
+}
+
+// 获取热门页面 (路径, 访问次数)
+pub fn get_top_pages(since: u64, limit: usize) -> SqliteResult<Vec<(String, u64)>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    // 时间桶按天对齐，包含起始时间所在的那一天
+    let since = since - since % 86400;
+    let mut stmt = conn.prepare(
+        "SELECT path, SUM(views) AS total FROM page_views
+         WHERE bucket >= ?
+         GROUP BY path
+         ORDER BY total DESC, path
+         LIMIT ?"
+    )?;
+    let rows = stmt.query_map(rusqlite::params![since, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 获取主要来源域名 (域名, 访问次数)
+pub fn get_top_referrers(since: u64, limit: usize) -> SqliteResult<Vec<(String, u64)>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let since = since - since % 86400;
+    let mut stmt = conn.prepare(
+        "SELECT host, SUM(visits) AS total FROM referrer_visits
+         WHERE bucket >= ?
+         GROUP BY host
+         ORDER BY total DESC, host
+         LIMIT ?"
+    )?;
+    let rows = stmt.query_map(rusqlite::params![since, limit], |row| {
+        Ok((row.get(0)?, row.get(1)?))
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 导出所有访客记录
+pub fn export_ip_visits() -> SqliteResult<Vec<IpVisitExport>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let mut stmt = conn.prepare(
+        "SELECT ip, visit_count, continent_code, continent_name, country_code, country_name,
+                state_prov, city, last_visit, latitude, longitude
+         FROM ip_visits ORDER BY ip"
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(IpVisitExport {
+            ip: row.get(0)?,
+            visit_count: row.get(1)?,
+            continent_code: row.get(2)?,
+            continent_name: row.get(3)?,
+            country_code: row.get(4)?,
+            country_name: row.get(5)?,
+            state_prov: row.get(6)?,
+            city: row.get(7)?,
+            last_visit: row.get(8)?,
+            latitude: row.get(9)?,
+            longitude: row.get(10)?,
+        })
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 在一个事务中合并导入的访客数据：相同IP的访问次数相加，
+// 最后访问时间取较晚者，已有的地理位置优先保留
+pub fn import_visitor_data(total_visits: u64, records: &[IpVisitExport]) -> SqliteResult<ImportSummary> {
+    let conn = get_db_conn();
+    let mut conn = conn.lock().expect("无法获取数据库锁");
+    
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    let mut summary = ImportSummary::default();
+    
+    tx.execute("INSERT OR IGNORE INTO visitor_stats (id, total_visits) VALUES (1, 0)", [])?;
+    tx.execute(
+        "UPDATE visitor_stats SET total_visits = total_visits + ? WHERE id = 1",
+        [total_visits],
+    )?;
+    summary.total_visits_added = total_visits;
+    
+    for record in records {
+        let exists: bool = tx.query_row(
+            "SELECT 1 FROM ip_visits WHERE ip = ?",
+            [&record.ip],
+            |_| Ok(true),
+        ).unwrap_or(false);
+        
+        if exists {
+            tx.execute(
+                "UPDATE ip_visits SET
+                    visit_count = visit_count + ?2,
+                    last_visit = MAX(COALESCE(last_visit, 0), COALESCE(?3, 0)),
+                    continent_code = COALESCE(continent_code, ?4),
+                    continent_name = COALESCE(continent_name, ?5),
+                    country_code = COALESCE(country_code, ?6),
+                    country_name = COALESCE(country_name, ?7),
+                    state_prov = COALESCE(state_prov, ?8),
+                    city = COALESCE(city, ?9),
+                    latitude = COALESCE(latitude, ?10),
+                    longitude = COALESCE(longitude, ?11)
+                 WHERE ip = ?1",
+                rusqlite::params![
+                    record.ip, record.visit_count, record.last_visit,
+                    record.continent_code, record.continent_name,
+                    record.country_code, record.country_name,
+                    record.state_prov, record.city,
+                    record.latitude, record.longitude
+                ],
+            )?;
+            summary.merged += 1;
+        } else {
+            tx.execute(
+                "INSERT INTO ip_visits (ip, visit_count, last_visit, continent_code, continent_name,
+                    country_code, country_name, state_prov, city, latitude, longitude)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                rusqlite::params![
+                    record.ip, record.visit_count, record.last_visit,
+                    record.continent_code, record.continent_name,
+                    record.country_code, record.country_name,
+                    record.state_prov, record.city,
+                    record.latitude, record.longitude
+                ],
+            )?;
+            summary.inserted += 1;
+        }
+    }
+    
+    tx.commit()?;
+    Ok(summary)
+}
+
+// 递增爬虫访问次数
+pub fn increment_bot_visit(pattern: &str, timestamp: u64) -> SqliteResult<()> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "INSERT INTO bot_visits (pattern, visits, last_visit)
+         VALUES (?1, 1, ?2)
+         ON CONFLICT(pattern) DO UPDATE SET visits = visits + 1, last_visit = excluded.last_visit",
+        rusqlite::params![pattern, timestamp],
+    )?;
+    
+    Ok(())
+}
+
+// 获取爬虫访问总次数
+pub fn get_bot_visit_count() -> SqliteResult<u64> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.query_row("SELECT COALESCE(SUM(visits), 0) FROM bot_visits", [], |row| row.get(0))
+}
+
+// 将内存中累计的访问计数在一个事务中写入数据库
+pub fn flush_visits(batch: &VisitBatch) -> SqliteResult<()> {
+    let conn = get_db_conn();
+    let mut conn = conn.lock().expect("无法获取数据库锁");
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    
+    if batch.total_visits > 0 {
+        tx.execute(
+            "INSERT INTO visitor_stats (id, total_visits) VALUES (1, ?1)
+             ON CONFLICT(id) DO UPDATE SET total_visits = total_visits + ?1",
+            [batch.total_visits],
+        )?;
+    }
+    
+    {
+        // 新的地理位置信息覆盖旧值，未解析到的字段保留原值
+        let mut stmt = tx.prepare(
+            "INSERT INTO ip_visits
+             (ip, visit_count, continent_code, continent_name, country_code, country_name, state_prov, city, last_visit, latitude, longitude)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(ip) DO UPDATE SET
+             visit_count = visit_count + excluded.visit_count,
+             last_visit = MAX(COALESCE(last_visit, 0), excluded.last_visit),
+             continent_code = COALESCE(excluded.continent_code, continent_code),
+             continent_name = COALESCE(excluded.continent_name, continent_name),
+             country_code = COALESCE(excluded.country_code, country_code),
+             country_name = COALESCE(excluded.country_name, country_name),
+             state_prov = COALESCE(excluded.state_prov, state_prov),
+             city = COALESCE(excluded.city, city),
+             latitude = COALESCE(excluded.latitude, latitude),
+             longitude = COALESCE(excluded.longitude, longitude)"
+        )?;
+        for (ip, visit) in batch.ips.iter() {
+            let geo = &visit.geo;
+            stmt.execute(rusqlite::params![
+                ip,
+                visit.visits,
+                geo.continent_code,
+                geo.continent_name,
+                geo.country_code,
+                geo.country_name,
+                geo.state_prov,
+                geo.city,
+                visit.last_visit,
+                geo.latitude,
+                geo.longitude,
+            ])?;
+        }
+        
+        // 小时桶同时累加到所在的天桶
+        let mut stmt = tx.prepare(
+            "INSERT INTO visit_buckets (granularity, bucket, ip, visits)
+             VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(granularity, bucket, ip) DO UPDATE SET visits = visits + ?4"
+        )?;
+        for ((hour, ip), visits) in batch.buckets.iter() {
+            stmt.execute(rusqlite::params!["hour", hour, ip, visits])?;
+            stmt.execute(rusqlite::params!["day", hour - hour % 86400, ip, visits])?;
+        }
+    }
+    
+    tx.commit()
+}
+
+// 清理超出保留期限的访问时间序列
+pub fn purge_visit_buckets(hour_before: u64, day_before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let hours = conn.execute(
+        "DELETE FROM visit_buckets WHERE granularity = 'hour' AND bucket < ?",
+        [hour_before],
+    )?;
+    let days = conn.execute(
+        "DELETE FROM visit_buckets WHERE granularity = 'day' AND bucket < ?",
+        [day_before],
+    )?;
+    
+    Ok(hours + days)
+}
+
+// 获取访问时间序列 (时间桶, 访问次数, 独立访客数)
+pub fn get_visit_history(granularity: &str, since: u64) -> SqliteResult<Vec<(u64, u64, u64)>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let mut stmt = conn.prepare(
+        "SELECT bucket, SUM(visits), COUNT(*) FROM visit_buckets
+         WHERE granularity = ? AND bucket >= ?
+         GROUP BY bucket
+         ORDER BY bucket"
+    )?;
+    let rows = stmt.query_map(rusqlite::params![granularity, since], |row| {
+        Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 保存一次服务监控检查结果
+pub fn record_monitor_check(check: &MonitorCheck) -> SqliteResult<()> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "INSERT INTO monitor_checks (monitor, timestamp, success, response_time_ms, status_code, error)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            check.monitor,
+            check.timestamp,
+            check.success,
+            check.response_time_ms,
+            check.status_code,
+            check.error
+        ],
+    )?;
+    
+    Ok(())
+}
+
+// 获取服务最近一次检查结果
+pub fn get_latest_monitor_check(monitor: &str) -> SqliteResult<Option<MonitorCheck>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    let result = conn.query_row(
+        "SELECT monitor, timestamp, success, response_time_ms, status_code, error
+         FROM monitor_checks WHERE monitor = ? ORDER BY timestamp DESC, id DESC LIMIT 1",
+        [monitor],
+        |row| {
+            Ok(MonitorCheck {
+                monitor: row.get(0)?,
+                timestamp: row.get(1)?,
+                success: row.get(2)?,
+                response_time_ms: row.get(3)?,
+                status_code: row.get(4)?,
+                error: row.get(5)?,
+            })
+        }
+    );
+    
+    match result {
+        Ok(check) => Ok(Some(check)),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+// 统计服务在指定时间之后的检查情况 (检查次数, 成功次数, 平均响应时间)
+pub fn get_monitor_uptime(monitor: &str, since: u64) -> SqliteResult<(u64, u64, Option<f64>)> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.query_row(
+        "SELECT COUNT(*), COALESCE(SUM(success), 0), AVG(response_time_ms)
+         FROM monitor_checks WHERE monitor = ? AND timestamp >= ?",
+        rusqlite::params![monitor, since],
+        |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+    )
+}
+
+// 清理服务超出保留期限的检查记录
+pub fn purge_monitor_checks(monitor: &str, before: u64) -> SqliteResult<usize> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    conn.execute(
+        "DELETE FROM monitor_checks WHERE monitor = ? AND timestamp < ?",
+        rusqlite::params![monitor, before],
+    )
+}
+
+// 创建事件或维护公告，并记录第一条进展，返回事件ID
+pub fn create_incident(incident: &Incident) -> SqliteResult<i64> {
+    let conn = get_db_conn();
+    let mut conn = conn.lock().expect("无法获取数据库锁");
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    
+    tx.execute(
+        "INSERT INTO incidents
+         (kind, title, severity, affected_services, status, created_at, scheduled_start, scheduled_end)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+        rusqlite::params![
+            incident.kind,
+            incident.title,
+            incident.severity,
+            incident.affected_services.join(","),
+            incident.status,
+            incident.created_at,
+            incident.scheduled_start,
+            incident.scheduled_end
+        ],
+    )?;
+    let id = tx.last_insert_rowid();
+    
+    for update in incident.updates.iter() {
+        tx.execute(
+            "INSERT INTO incident_updates (incident_id, timestamp, status, message) VALUES (?, ?, ?, ?)",
+            rusqlite::params![id, update.timestamp, update.status, update.message],
+        )?;
+    }
+    
+    tx.commit()?;
+    Ok(id)
+}
+
+// 添加事件进展并更新事件状态，事件不存在时返回false
+pub fn add_incident_update(id: i64, update: &IncidentUpdate, resolved: bool) -> SqliteResult<bool> {
+    let conn = get_db_conn();
+    let mut conn = conn.lock().expect("无法获取数据库锁");
+    let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+    
+    let changed = if resolved {
+        tx.execute(
+            "UPDATE incidents SET status = ?, resolved_at = ?, resolution = ? WHERE id = ? AND resolved_at IS NULL",
+            rusqlite::params![update.status, update.timestamp, update.message, id],
+        )?
+    } else {
+        tx.execute(
+            "UPDATE incidents SET status = ? WHERE id = ? AND resolved_at IS NULL",
+            rusqlite::params![update.status, id],
+        )?
+    };
+    
+    if changed == 0 {
+        return Ok(false);
+    }
+    
+    tx.execute(
+        "INSERT INTO incident_updates (incident_id, timestamp, status, message) VALUES (?, ?, ?, ?)",
+        rusqlite::params![id, update.timestamp, update.status, update.message],
+    )?;
+    
+    tx.commit()?;
+    Ok(true)
+}
+
+// 读取事件的进展记录
+fn load_incident_updates(conn: &Connection, id: i64) -> SqliteResult<Vec<IncidentUpdate>> {
+    let mut stmt = conn.prepare(
+        "SELECT timestamp, status, message FROM incident_updates WHERE incident_id = ? ORDER BY timestamp, id"
+    )?;
+    let rows = stmt.query_map([id], |row| {
+        Ok(IncidentUpdate {
+            timestamp: row.get(0)?,
+            status: row.get(1)?,
+            message: row.get(2)?,
+        })
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    
+    Ok(result)
+}
+
+// 按条件查询事件列表（包含进展记录）
+fn query_incidents(conn: &Connection, condition: &str, params: &[&dyn rusqlite::ToSql]) -> SqliteResult<Vec<Incident>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, kind, title, severity, affected_services, status, created_at,
+                scheduled_start, scheduled_end, resolved_at, resolution
+         FROM incidents WHERE {}",
+        condition
+    ))?;
+    let rows = stmt.query_map(params, |row| {
+        let services: String = row.get(4)?;
+        Ok(Incident {
+            id: row.get(0)?,
+            kind: row.get(1)?,
+            title: row.get(2)?,
+            severity: row.get(3)?,
+            affected_services: services
+                .split(',')
+                .filter(|service| !service.is_empty())
+                .map(|service| service.to_string())
+                .collect(),
+            status: row.get(5)?,
+            created_at: row.get(6)?,
+            scheduled_start: row.get(7)?,
+            scheduled_end: row.get(8)?,
+            resolved_at: row.get(9)?,
+            resolution: row.get(10)?,
+            updates: Vec::new(),
+        })
+    })?;
+    
+    let mut result = Vec::new();
+    for row in rows {
+        let mut incident = row?;
+        incident.updates = load_incident_updates(conn, incident.id)?;
+        result.push(incident);
+    }
+    
+    Ok(result)
+}
+
+// 获取指定事件
+pub fn get_incident(id: i64) -> SqliteResult<Option<Incident>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    Ok(query_incidents(&conn, "id = ?", &[&id])?.into_iter().next())
+}
+
+// 获取所有未解决的事件和未结束的维护
+pub fn get_open_incidents(now: u64) -> SqliteResult<Vec<Incident>> {
+    let conn = get_db_conn();
+    let conn = conn.lock().expect("无法获取数据库锁");
+    
+    query_incidents(
+        &conn,
+        "resolved_at IS NULL AND (scheduled_end IS NULL OR scheduled_end > ?)
+         ORDER BY COALESCE(scheduled_start, created_at)",
+        &[&now],
+    )
+}
+
+#[cfg(test)]
+pub mod test_support {
+    use std::sync::Once;
+
+    // 初始化测试共用的临时数据库，各测试应使用不同的键，并按前后差值断言
+    pub fn init_test_db() {
+        static INIT: Once = Once::new();
+        INIT.call_once(|| {
+            let path = std::env::temp_dir().join(format!("lycrex-home-test-{}.db", std::process::id()));
+            super::init_db(path.to_str().expect("临时目录路径无效")).expect("测试数据库初始化失败");
+        });
+    }
+}
@@ -0,0 +1,151 @@
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock};
+use std::time::Duration;
+use maxminddb::{geoip2, Reader};
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use once_cell::sync::Lazy;
+use rimplog::info;
+use crate::config::get_config;
+
+// 地理位置查询结果
+#[derive(Debug, Clone, Default)]
+pub struct GeoLocation {
+    pub continent_code: Option<String>,
+    pub continent_name: Option<String>,
+    pub country_code: Option<String>,
+    pub country_name: Option<String>,
+    pub state_prov: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+}
+
+// 当前加载的mmdb数据库
+static GEOIP_READER: Lazy<RwLock<Option<Reader<Vec<u8>>>>> = Lazy::new(|| RwLock::new(None));
+
+// 文件变化后等待的时间，合并同一次文件替换产生的多个事件
+const RELOAD_DELAY: Duration = Duration::from_millis(200);
+
+// 是否已有等待执行的重新加载
+static RELOAD_PENDING: AtomicBool = AtomicBool::new(false);
+
+// 加载mmdb数据库文件
+fn load_database(path: &Path) {
+    match Reader::open_readfile(path) {
+        Ok(reader) => {
+            info!("GeoIP数据库已加载: {} ({})", path.display(), reader.metadata().database_type);
+            if let Ok(mut current) = GEOIP_READER.write() {
+                *current = Some(reader);
+            }
+        }
+        Err(e) => {
+            info!("加载GeoIP数据库失败: {}: {}", path.display(), e);
+        }
+    }
+}
+
+// 初始化GeoIP数据库，并在文件变化时自动重新加载
+pub fn init_geoip() {
+    let geoip_config = get_config().geoip.clone();
+    if !geoip_config.enabled {
+        info!("GeoIP查询已禁用");
+        return;
+    }
+
+    let path = PathBuf::from(&geoip_config.database_path);
+    if path.exists() {
+        load_database(&path);
+    } else {
+        info!("GeoIP数据库不存在: {}，放置文件后将自动加载", path.display());
+    }
+
+    if let Err(e) = start_geoip_watcher(path) {
+        info!("启动GeoIP数据库监听失败: {}", e);
+    }
+}
+
+// 监听数据库所在目录，文件通常通过替换方式更新
+fn start_geoip_watcher(path: PathBuf) -> notify::Result<()> {
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    if !directory.exists() {
+        std::fs::create_dir_all(&directory).map_err(notify::Error::io)?;
+    }
+
+    let file_name = path.file_name().map(|name| name.to_os_string());
+
+    let watched_path = path.clone();
+    let mut watcher = notify::recommended_watcher(move |res: Result<Event, notify::Error>| {
+        match res {
+            Ok(event) => {
+                if !matches!(event.kind, EventKind::Modify(_) | EventKind::Create(_)) {
+                    return;
+                }
+                if !event.paths.iter().any(|changed| changed.file_name() == file_name.as_deref()) {
+                    return;
+                }
+
+                // 延迟加载，等待期间的事件合并为一次，加载开始后的事件会再触发一次，确保读取到最后一次写入
+                if RELOAD_PENDING.swap(true, Ordering::SeqCst) {
+                    return;
+                }
+                let watched_path = watched_path.clone();
+                std::thread::spawn(move || {
+                    std::thread::sleep(RELOAD_DELAY);
+                    RELOAD_PENDING.store(false, Ordering::SeqCst);
+                    info!("检测到GeoIP数据库变更，正在重新加载...");
+                    load_database(&watched_path);
+                });
+            }
+            Err(e) => info!("监听GeoIP数据库错误: {:?}", e),
+        }
+    })?;
+
+    watcher.watch(&directory, RecursiveMode::NonRecursive)?;
+
+    static WATCHER: OnceLock<Arc<Mutex<Box<dyn notify::Watcher + Send>>>> = OnceLock::new();
+    WATCHER.set(Arc::new(Mutex::new(Box::new(watcher)))).unwrap_or(());
+
+    Ok(())
+}
+
+// 按配置的语言选择名称，缺失时回退到英文
+fn pick_name(names: &geoip2::Names, language: &str) -> Option<String> {
+    let localized = match language {
+        "de" => names.german,
+        "es" => names.spanish,
+        "fr" => names.french,
+        "ja" => names.japanese,
+        "pt-BR" => names.brazilian_portuguese,
+        "ru" => names.russian,
+        "zh-CN" => names.simplified_chinese,
+        _ => None,
+    };
+
+    localized.or(names.english).map(|name| name.to_string())
+}
+
+// 查询IP的地理位置，数据库未加载或查询失败时返回None
+pub fn lookup(ip: &str) -> Option<GeoLocation> {
+    let ip: IpAddr = ip.parse().ok()?;
+    let reader = GEOIP_READER.read().ok()?;
+    let reader = reader.as_ref()?;
+
+    let city = reader.lookup(ip).ok()?.decode::<geoip2::City>().ok()??;
+    let language = get_config().geoip.language.clone();
+
+    Some(GeoLocation {
+        continent_code: city.continent.code.map(|code| code.to_string()),
+        continent_name: pick_name(&city.continent.names, &language),
+        country_code: city.country.iso_code.map(|code| code.to_string()),
+        country_name: pick_name(&city.country.names, &language),
+        state_prov: city.subdivisions.first().and_then(|subdivision| pick_name(&subdivision.names, &language)),
+        city: pick_name(&city.city.names, &language),
+        latitude: city.location.latitude,
+        longitude: city.location.longitude,
+    })
+}
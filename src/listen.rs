@@ -0,0 +1,165 @@
+use std::fmt;
+use std::fs;
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::net::SocketAddr;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use axum::Router;
+use rimplog::info;
+use socket2::{Domain, Socket, Type};
+use tokio::net::{TcpListener, UnixListener};
+use crate::api::client_ip::PeerAddr;
+use crate::proxy_protocol::ProxyProtocolListener;
+
+// 监听地址，"unix:" 开头的是Unix套接字路径，其余为 IP:端口 或 [IPv6]:端口
+#[derive(Debug, Clone, PartialEq)]
+pub enum ListenAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl ListenAddr {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        match value.strip_prefix("unix:") {
+            Some("") => Err("unix: 后需要套接字文件路径".to_string()),
+            Some(path) => Ok(Self::Unix(PathBuf::from(path))),
+            None => value.parse::<SocketAddr>()
+                .map(Self::Tcp)
+                .map_err(|_| format!("无效的监听地址: {}，应为 IP:端口、[IPv6]:端口 或 unix:/路径", value)),
+        }
+    }
+}
+
+impl fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tcp(addr) => write!(f, "http://{}", addr),
+            Self::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+// 解析八进制的Unix套接字权限，例如 "660"
+pub fn parse_socket_mode(value: &str) -> Result<u32, String> {
+    u32::from_str_radix(value.trim(), 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("无效的Unix套接字权限: {}，应为八进制，例如 660", value))
+}
+
+// 在单个监听地址上运行的服务器
+pub type ServeFuture = Pin<Box<dyn Future<Output = io::Result<()>> + Send>>;
+
+// 绑定监听地址，返回在该地址上运行路由的服务器，PROXY协议只用于TCP地址
+pub async fn bind(addr: &ListenAddr, app: Router, proxy_protocol: bool, socket_mode: u32) -> io::Result<ServeFuture> {
+    let service = app.into_make_service_with_connect_info::<PeerAddr>();
+
+    match addr {
+        ListenAddr::Tcp(addr) => {
+            let listener = bind_tcp(addr)?;
+            if proxy_protocol {
+                // 启用PROXY协议时，连接地址取自协议头中的客户端地址
+                let listener = ProxyProtocolListener::new(listener)?;
+                Ok(Box::pin(axum::serve(listener, service).into_future()))
+            } else {
+                Ok(Box::pin(axum::serve(listener, service).into_future()))
+            }
+        }
+        ListenAddr::Unix(path) => {
+            remove_stale_socket(path)?;
+            let listener = bind_unix(path, socket_mode)?;
+            Ok(Box::pin(axum::serve(listener, service).into_future()))
+        }
+    }
+}
+
+// 绑定TCP地址，IPv6地址只接受IPv6连接，避免与同端口的IPv4监听冲突
+fn bind_tcp(addr: &SocketAddr) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(*addr), Type::STREAM, None)?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.set_nonblocking(true)?;
+    socket.bind(&(*addr).into())?;
+    socket.listen(1024)?;
+    TcpListener::from_std(socket.into())
+}
+
+// 在umask下创建套接字文件，文件创建时即为配置的权限，不存在权限过宽的时间窗口
+fn bind_unix(path: &Path, socket_mode: u32) -> io::Result<UnixListener> {
+    let mask = (0o777 & !socket_mode) as libc::mode_t;
+    // SAFETY: umask 只修改进程的文件创建掩码，绑定后立即恢复
+    let previous = unsafe { libc::umask(mask) };
+    let result = UnixListener::bind(path);
+    unsafe { libc::umask(previous) };
+    result
+}
+
+// 删除上次运行遗留的套接字文件，套接字仍有进程在监听或路径上是其他文件时报错
+fn remove_stale_socket(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_socket() => {
+            if UnixStream::connect(path).is_ok() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AddrInUse,
+                    format!("{} 已有进程在监听", path.display()),
+                ));
+            }
+            fs::remove_file(path)
+        }
+        Ok(_) => Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} 已存在且不是套接字", path.display()),
+        )),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+// 关闭服务器后删除Unix套接字文件
+pub fn remove_sockets(addrs: &[ListenAddr]) {
+    for addr in addrs {
+        if let ListenAddr::Unix(path) = addr {
+            if let Err(e) = fs::remove_file(path) {
+                info!("删除套接字文件失败: {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    #[tokio::test]
+    async fn ipv6_listener_is_v6_only() {
+        let addr: SocketAddr = "[::1]:0".parse().unwrap();
+        // 环境不支持IPv6时跳过
+        let Ok(listener) = bind_tcp(&addr) else { return };
+        let socket = socket2::SockRef::from(&listener);
+        assert!(socket.only_v6().unwrap());
+
+        let v4 = bind_tcp(&"127.0.0.1:0".parse().unwrap()).unwrap();
+        assert!(v4.local_addr().unwrap().is_ipv4());
+    }
+
+    #[tokio::test]
+    async fn unix_socket_is_created_with_mode() {
+        let dir = std::env::temp_dir().join(format!("lycrex-listen-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("test.sock");
+        let _ = fs::remove_file(&path);
+
+        let _listener = bind_unix(&path, 0o660).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode, 0o660);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,8 +1,8 @@
 use rimplog::{init_logger, LoggerBuilder, LoggerPreset};
 
-pub fn init_log() {
+pub fn init_log(level: &str) {
     let logger = LoggerBuilder {
-        level: "INFO".to_string(),
+        level: level.to_uppercase(),
         only_project_logs: false,
         path_depth: 0,
         time_format: "%Y-%m-%d %H:%M:%S".to_string(),
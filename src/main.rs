@@ -3,14 +3,26 @@ mod api;
 mod static_files;
 mod config;
 mod db;
+mod migrations;
+mod backup;
+mod storage;
 mod profile;
+mod geoip;
+mod proxy_protocol;
+mod listen;
+mod cli;
 
 use log::init_log;
-use config::{init_config, get_server_config, start_config_watcher};
-use api::status::init_server_config;
+use config::{init_config, get_config, get_server_config, set_config_source, start_config_watcher};
+use api::status::{start_node_poller, start_status_pusher};
 use api::visitor::{init_visitor_stats, save_stats, start_periodic_save};
+use api::metrics::start_metrics_sampler;
+use api::monitor::start_monitors;
 use db::init_db;
+use storage::SharedStorage;
+use listen::ListenAddr;
 
+use clap::Parser;
 use rimplog::info;
 use std::sync::Arc;
 use tokio::signal;
@@ -20,9 +32,8 @@ use axum::{
     Router,
     response::Html,
     extract::Extension,
+    middleware,
 };
-use tokio::net::TcpListener;
-use std::net::SocketAddr;
 use tower_http::cors::{CorsLayer, Any};
 use std::sync::Mutex;
 use reqwest::Client;
@@ -34,49 +45,87 @@ const INDEX_HTML: &str = include_str!("../home/index.html");
 
 #[tokio::main]
 async fn main() {
+    let mut cli = cli::Cli::parse();
+    set_config_source(cli.config.clone(), cli.data_dir.clone());
+    
+    // 除serve外的子命令执行完即退出
+    match cli.command.take() {
+        None | Some(cli::Command::Serve) => {}
+        Some(command) => std::process::exit(cli::run(command, &cli.log_level)),
+    }
+    
     // 初始化应用
-    init_application().await;
+    let storage = init_application(&cli.log_level).await;
     
-    // 创建并运行服务器
-    let app = create_router();
-    let addr = SocketAddr::from(([0, 0, 0, 0], get_server_config().port));
-    info!("服务器运行在: http://{}", addr);
+    // 命令行指定的监听地址优先于配置
+    let addrs = if cli.bind.is_empty() {
+        get_server_config().listen_addrs().expect("监听地址已在加载配置时校验")
+    } else {
+        cli.bind
+    };
+    
+    // 创建并运行服务器，所有监听地址使用同一个路由
+    let app = create_router(storage.clone());
     
     // 运行服务器直到接收到关闭信号
-    run_server(app, addr).await;
+    run_server(app, addrs, storage).await;
 }
 
 // 初始化应用程序
-async fn init_application() {
-    init_log();
+async fn init_application(log_level: &str) -> SharedStorage {
+    init_log(log_level);
     
     // 初始化配置
     if let Err(e) = init_config() {
-        panic!("配置初始化失败: {}", e);
+        eprintln!("配置初始化失败: {}", e);
+        std::process::exit(1);
     }
 
-    // 初始化服务器配置
-    init_server_config();
+    // 初始化受信任代理
+    api::client_ip::set_trusted_proxies(&get_server_config().trusted_proxies);
     
     // 初始化数据库
-    if let Err(e) = init_db("data/app.db") {
+    if let Err(e) = init_db(&get_config().database.path) {
         panic!("数据库初始化失败: {}", e);
     }
     
+    // 加载本地GeoIP数据库
+    geoip::init_geoip();
+    
+    // 创建存储
+    let storage = storage::create_storage();
+    
     // 初始化访问统计
     init_visitor_stats();
     
     // 启动定时保存功能 - 每5分钟保存一次
-    start_periodic_save(300);
+    start_periodic_save(storage.clone(), 300);
+    
+    // 启动数据库定时备份
+    backup::start_backup_scheduler(storage.clone());
+    
+    // 启动系统指标采样
+    start_metrics_sampler();
+    
+    // 启动对等节点状态轮询
+    start_node_poller();
+    
+    // 启动状态推送
+    start_status_pusher();
+    
+    // 启动服务可用性监控
+    start_monitors();
     
     // 启动配置文件监听
     if let Err(e) = start_config_watcher() {
         info!("启动配置文件监听失败: {}", e);
     }
+    
+    storage
 }
 
 // 创建应用路由
-fn create_router() -> Router {
+fn create_router(storage: SharedStorage) -> Router {
     // 配置 CORS
     let cors = CorsLayer::new()
         .allow_origin(Any)
@@ -92,7 +141,7 @@ fn create_router() -> Router {
     
     // 创建路由
     Router::new()
-        .route("/", get(handler))
+        .route("/", get(handler).layer(middleware::from_fn_with_state(storage.clone(), api::visitor::track_visit)))
         .route("/static/{*path}", get(static_files::serve_static_file))
         .nest("/api", api::api_routes())
 
@@ -118,14 +167,16 @@ fn create_router() -> Router {
             .route("/api/change-username", put(profile::handlers::change_username_api))
         )
         // 全局状态
+        .with_state(storage)
         .layer(Extension(client_state))
         .layer(Extension(processed_codes))
+        .layer(middleware::from_fn(api::analytics::track_page_view))
         .layer(CookieManagerLayer::new())
         .layer(cors)
 }
 
 // 运行服务器并处理关闭信号
-async fn run_server(app: Router, addr: SocketAddr) {
+async fn run_server(app: Router, addrs: Vec<ListenAddr>, storage: SharedStorage) {
     // 注册关闭信号处理
     let (tx, rx) = tokio::sync::oneshot::channel::<()>();
     let tx = Arc::new(std::sync::Mutex::new(Some(tx)));
@@ -146,21 +197,44 @@ async fn run_server(app: Router, addr: SocketAddr) {
         }
     });
     
-    // 创建服务器并运行，直到收到关闭信号
-    let server = axum::serve(
-        TcpListener::bind(addr).await.unwrap(), 
-        app.into_make_service_with_connect_info::<SocketAddr>()
-    );
+    // 在每个监听地址上创建服务器，任意一个绑定失败时退出
+    let server_config = get_server_config();
+    let socket_mode = listen::parse_socket_mode(&server_config.unix_socket_mode)
+        .expect("套接字权限已在加载配置时校验");
+    if server_config.proxy_protocol {
+        info!("已启用PROXY协议，TCP连接必须来自受信任代理并携带PROXY协议头");
+    }
+    
+    let mut servers = Vec::new();
+    for addr in &addrs {
+        match listen::bind(addr, app.clone(), server_config.proxy_protocol, socket_mode).await {
+            Ok(server) => {
+                info!("服务器运行在: {}", addr);
+                servers.push(server);
+            }
+            Err(e) => {
+                eprintln!("无法监听 {}: {}", addr, e);
+                listen::remove_sockets(&addrs[..servers.len()]);
+                std::process::exit(1);
+            }
+        }
+    }
+    let server = futures::future::select_all(servers);
     
     tokio::select! {
-        _ = server => info!("服务器停止"),
+        (result, _, _) = server => match result {
+            Ok(()) => info!("服务器停止"),
+            Err(e) => info!("服务器异常停止: {}", e),
+        },
         _ = rx => {
             info!("正在关闭服务器...");
-            // 保存访问者统计数据
-            save_stats();
+            // 写入内存中尚未保存的访问者统计数据
+            db::run(move || save_stats(&*storage)).await;
             info!("服务器已安全关闭");
         }
     }
+    
+    listen::remove_sockets(&addrs);
 }
 
 // 处理首页请求
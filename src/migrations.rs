@@ -0,0 +1,187 @@
+use rusqlite::{Connection, Result as SqliteResult};
+use rimplog::info;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// 迁移步骤：SQL脚本或需要判断现有结构的代码
+enum Step {
+    Sql(&'static str),
+    Code(fn(&Connection) -> SqliteResult<()>),
+}
+
+// 数据库迁移，按版本号顺序执行，已发布的迁移不能修改，只能追加
+struct Migration {
+    version: u32,
+    description: &'static str,
+    step: Step,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "baseline",
+        step: Step::Sql(include_str!("../migrations/0001_baseline.sql")),
+    },
+    Migration {
+        version: 2,
+        description: "status history",
+        step: Step::Sql(include_str!("../migrations/0002_status_history.sql")),
+    },
+    Migration {
+        version: 3,
+        description: "visitor analytics",
+        step: Step::Sql(include_str!("../migrations/0003_visitor_analytics.sql")),
+    },
+    Migration {
+        version: 4,
+        description: "ip_visits coordinates",
+        step: Step::Code(add_ip_visit_coordinates),
+    },
+];
+
+// 构造迁移错误
+fn migration_error(message: String) -> rusqlite::Error {
+    rusqlite::Error::SqliteFailure(
+        rusqlite::ffi::Error {
+            code: rusqlite::ffi::ErrorCode::Unknown,
+            extended_code: 0,
+        },
+        Some(message),
+    )
+}
+
+// 表中不存在指定列时添加该列
+fn add_column_if_missing(conn: &Connection, table: &str, column: &str, definition: &str) -> SqliteResult<()> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    let exists = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .filter_map(|name| name.ok())
+        .any(|name| name == column);
+
+    if !exists {
+        conn.execute(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition), [])?;
+    }
+
+    Ok(())
+}
+
+// 访客记录增加坐标列，之前的版本可能已经添加过
+fn add_ip_visit_coordinates(conn: &Connection) -> SqliteResult<()> {
+    add_column_if_missing(conn, "ip_visits", "latitude", "REAL")?;
+    add_column_if_missing(conn, "ip_visits", "longitude", "REAL")
+}
+
+// 程序支持的最新结构版本
+pub fn latest_version() -> u32 {
+    MIGRATIONS.last().map(|migration| migration.version).unwrap_or(0)
+}
+
+// 获取数据库当前的结构版本
+pub fn current_version(conn: &Connection) -> SqliteResult<u32> {
+    conn.query_row("SELECT COALESCE(MAX(version), 0) FROM schema_version", [], |row| row.get(0))
+}
+
+// 执行未应用的迁移，每个迁移在独立的事务中执行
+// 数据库版本高于程序支持的版本时拒绝启动，避免旧程序破坏新结构
+pub fn run_migrations(conn: &mut Connection) -> SqliteResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at INTEGER NOT NULL
+        )",
+        [],
+    )?;
+
+    let current = current_version(conn)?;
+    let latest = latest_version();
+    if current > latest {
+        return Err(migration_error(format!(
+            "数据库结构版本 {} 高于程序支持的版本 {}，请升级程序后再启动",
+            current, latest
+        )));
+    }
+
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        info!("执行数据库迁移 {}: {}", migration.version, migration.description);
+
+        let tx = conn.transaction()?;
+        match migration.step {
+            Step::Sql(sql) => tx.execute_batch(sql)?,
+            Step::Code(apply) => apply(&tx)?,
+        }
+
+        let applied_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        tx.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, ?, ?)",
+            rusqlite::params![migration.version, migration.description, applied_at],
+        )?;
+        tx.commit()?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count(conn: &Connection, sql: &str) -> i64 {
+        conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn upgrades_unversioned_database() {
+        // 引入迁移之前的数据库：只有初始结构，部分旧版本已经添加过坐标列
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(include_str!("../migrations/0001_baseline.sql")).unwrap();
+        conn.execute_batch(
+            "ALTER TABLE ip_visits ADD COLUMN latitude REAL;
+             INSERT INTO visitor_stats (id, total_visits) VALUES (1, 42);
+             INSERT INTO ip_visits (ip, visit_count, city, last_visit, latitude) VALUES ('192.0.2.1', 7, 'Paris', 1000, 48.85);
+             INSERT INTO user_notes (user_id, content, last_updated) VALUES ('u1', 'note', 1000);"
+        ).unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+        assert_eq!(count(&conn, "SELECT total_visits FROM visitor_stats WHERE id = 1"), 42);
+        assert_eq!(count(&conn, "SELECT visit_count FROM ip_visits WHERE ip = '192.0.2.1'"), 7);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM user_notes"), 1);
+        let (city, latitude, longitude): (String, f64, Option<f64>) = conn.query_row(
+            "SELECT city, latitude, longitude FROM ip_visits WHERE ip = '192.0.2.1'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        ).unwrap();
+        assert_eq!((city.as_str(), latitude, longitude), ("Paris", 48.85, None));
+    }
+
+    #[test]
+    fn second_run_is_noop() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute("UPDATE schema_version SET applied_at = 1", []).unwrap();
+
+        run_migrations(&mut conn).unwrap();
+
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM schema_version"), MIGRATIONS.len() as i64);
+        assert_eq!(count(&conn, "SELECT COUNT(*) FROM schema_version WHERE applied_at != 1"), 0);
+        assert_eq!(current_version(&conn).unwrap(), latest_version());
+    }
+
+    #[test]
+    fn refuses_newer_schema() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        conn.execute(
+            "INSERT INTO schema_version (version, description, applied_at) VALUES (?, 'future', 0)",
+            [latest_version() + 1],
+        ).unwrap();
+
+        let error = run_migrations(&mut conn).unwrap_err();
+        assert!(error.to_string().contains("高于程序支持的版本"));
+        assert_eq!(current_version(&conn).unwrap(), latest_version() + 1);
+    }
+}
@@ -2,7 +2,7 @@ use crate::config::get_oauth_config;
 use super::models::ProcessedCodes;
 use crate::profile::utils;
 use axum::{
-    extract::{Query, Multipart},
+    extract::{Query, Multipart, State},
     response::{Redirect, Json, IntoResponse},
     http::StatusCode,
     Extension,
@@ -12,8 +12,8 @@ use rimplog::{error, info};
 use serde_json::{self, json};
 use std::sync::{Arc, Mutex};
 use tower_cookies::{Cookie, Cookies};
-use crate::db::{get_user_note, save_user_note, get_public_note, save_public_note, 
-               get_user_channel_setting, save_user_channel_setting};
+use crate::db;
+use crate::storage::SharedStorage;
 use std::time::Duration;
 
 type ClientState = Arc<reqwest::Client>;
@@ -492,6 +492,7 @@ pub async fn logout(cookies: Cookies) -> impl IntoResponse {
 pub async fn save_user_notes_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     // 检查是否已登录
@@ -538,7 +539,8 @@ pub async fn save_user_notes_api(
         .as_secs();
 
     // 保存备忘录到数据库
-    match save_user_note(&user_id, content) {
+    let content = content.to_string();
+    match db::run(move || storage.save_user_note(&user_id, &content)).await {
         Ok(_) => {
             // info!("用户 {} 的备忘录已保存", user_id);
             
@@ -562,6 +564,7 @@ pub async fn save_user_notes_api(
 pub async fn get_user_notes_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
 ) -> impl IntoResponse {
     // 检查是否已登录
     let access_token = match cookies.get("access_token") {
@@ -584,7 +587,7 @@ pub async fn get_user_notes_api(
     };
 
     // 从数据库获取备忘录
-    match get_user_note(&user_id) {
+    match db::run(move || storage.get_user_note(&user_id)).await {
         Ok(Some(note)) => {
             (StatusCode::OK, Json(json!({
                 "content": note.content,
@@ -917,6 +920,7 @@ pub async fn change_username_api(
 pub async fn save_public_notes_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     // 检查是否已登录
@@ -979,7 +983,8 @@ pub async fn save_public_notes_api(
         .as_secs();
 
     // 保存公共备忘录到数据库
-    match save_public_note(channel_id, content) {
+    let content = content.to_string();
+    match db::run(move || storage.save_public_note(channel_id, &content)).await {
         Ok(_) => {
             // info!("用户 {} 保存了频道 {} 的公共备忘录", user_id, channel_id);
             
@@ -1003,6 +1008,7 @@ pub async fn save_public_notes_api(
 pub async fn get_public_notes_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
     // 检查是否已登录
@@ -1041,7 +1047,7 @@ pub async fn get_public_notes_api(
     };
 
     // 从数据库获取公共备忘录
-    match get_public_note(channel_id) {
+    match db::run(move || storage.get_public_note(channel_id)).await {
         Ok(Some(note)) => {
             (StatusCode::OK, Json(json!({
                 "content": note.content,
@@ -1067,6 +1073,7 @@ pub async fn get_public_notes_api(
 pub async fn save_user_channel_setting_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
     Json(payload): Json<serde_json::Value>,
 ) -> impl IntoResponse {
     // 检查是否已登录
@@ -1107,7 +1114,7 @@ pub async fn save_user_channel_setting_api(
     };
 
     // 保存用户频道设置到数据库
-    match save_user_channel_setting(&user_id, channel_id) {
+    match db::run(move || storage.save_channel_setting(&user_id, channel_id)).await {
         Ok(_) => {
             // 返回成功状态
             (StatusCode::OK, Json(json!({
@@ -1128,6 +1135,7 @@ pub async fn save_user_channel_setting_api(
 pub async fn get_user_channel_setting_api(
     cookies: Cookies,
     Extension(client): Extension<ClientState>,
+    State(storage): State<SharedStorage>,
 ) -> impl IntoResponse {
     // 检查是否已登录
     let access_token = match cookies.get("access_token") {
@@ -1150,7 +1158,7 @@ pub async fn get_user_channel_setting_api(
     };
 
     // 从数据库获取用户频道设置
-    match get_user_channel_setting(&user_id) {
+    match db::run(move || storage.get_channel_setting(&user_id)).await {
         Ok(Some(setting)) => {
             (StatusCode::OK, Json(json!({
                 "channel_id": setting.channel_id,
@@ -0,0 +1,342 @@
+use axum::serve::Listener;
+use rimplog::{debug, info};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, timeout, Duration};
+use crate::api::client_ip;
+
+// 等待PROXY协议头的最长时间
+const HEADER_TIMEOUT: Duration = Duration::from_secs(5);
+
+// v1协议头最大长度（含结尾的\r\n）
+const V1_MAX_LENGTH: usize = 107;
+
+// v2协议签名
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+// 支持PROXY协议的监听器：在接受连接时解析协议头，并将真实客户端地址作为连接地址
+pub struct ProxyProtocolListener {
+    receiver: mpsc::Receiver<(TcpStream, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl ProxyProtocolListener {
+    // 只接受来自受信任代理的连接，其他连接可以伪造协议头中的客户端地址
+    pub fn new(listener: TcpListener) -> io::Result<Self> {
+        Self::with_trust_check(listener, client_ip::is_trusted_proxy)
+    }
+
+    fn with_trust_check(listener: TcpListener, is_trusted: fn(&IpAddr) -> bool) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (sender, receiver) = mpsc::channel(128);
+
+        // 在独立任务中接受连接并解析协议头，避免慢连接阻塞其他连接
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, peer) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        info!("接受连接失败: {}", e);
+                        sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                if !is_trusted(&peer.ip()) {
+                    debug!("拒绝来自 {} 的连接，不是受信任的代理", peer);
+                    continue;
+                }
+
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match timeout(HEADER_TIMEOUT, read_header(&mut stream)).await {
+                        Ok(Ok(source)) => {
+                            // LOCAL命令或未知地址族时使用连接地址
+                            let _ = sender.send((stream, source.unwrap_or(peer))).await;
+                        }
+                        Ok(Err(e)) => debug!("拒绝来自 {} 的连接，PROXY协议头无效: {}", peer, e),
+                        Err(_) => debug!("拒绝来自 {} 的连接，等待PROXY协议头超时", peer),
+                    }
+                });
+            }
+        });
+
+        Ok(Self { receiver, local_addr })
+    }
+}
+
+impl Listener for ProxyProtocolListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.receiver.recv().await {
+            Some(accepted) => accepted,
+            // 接受任务不会退出，这里只是兜底
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+// 构造协议错误
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// 协议头解析结果
+#[derive(Debug, PartialEq)]
+enum Header {
+    // 数据不足，至少需要这么多字节才能继续解析
+    Incomplete(usize),
+    // 协议头长度和客户端地址
+    Complete(usize, Option<SocketAddr>),
+}
+
+// 读取并解析PROXY协议头，先peek到缓冲区中解析，再只消耗协议头本身，不会读走后续的HTTP数据
+async fn read_header(stream: &mut TcpStream) -> io::Result<Option<SocketAddr>> {
+    let mut buf = vec![0u8; V1_MAX_LENGTH];
+    loop {
+        let peeked = stream.peek(&mut buf).await?;
+        if peeked == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        match parse_header(&buf[..peeked])? {
+            Header::Complete(length, source) => {
+                stream.read_exact(&mut buf[..length]).await?;
+                return Ok(source);
+            }
+            // v2协议头可能超过缓冲区大小
+            Header::Incomplete(needed) if needed > buf.len() && peeked == buf.len() => buf.resize(needed, 0),
+            // 协议头还没有完整到达，稍后再看
+            Header::Incomplete(_) => sleep(Duration::from_millis(10)).await,
+        }
+    }
+}
+
+// 从已收到的数据中解析协议头
+fn parse_header(buf: &[u8]) -> io::Result<Header> {
+    if starts_with(buf, b"PROXY ") {
+        parse_v1(buf)
+    } else if starts_with(buf, &V2_SIGNATURE) {
+        parse_v2(buf)
+    } else {
+        Err(invalid("缺少PROXY协议头"))
+    }
+}
+
+// 数据是否以指定前缀开头，数据较短时只比较已收到的部分
+fn starts_with(buf: &[u8], prefix: &[u8]) -> bool {
+    let length = buf.len().min(prefix.len());
+    buf[..length] == prefix[..length]
+}
+
+// 解析v1文本协议头，例如 "PROXY TCP4 192.0.2.1 192.0.2.2 56324 443\r\n"
+fn parse_v1(buf: &[u8]) -> io::Result<Header> {
+    let limit = buf.len().min(V1_MAX_LENGTH);
+    let end = match buf[..limit].iter().position(|byte| *byte == b'\n') {
+        Some(end) => end + 1,
+        None if buf.len() >= V1_MAX_LENGTH => return Err(invalid("v1协议头过长")),
+        None => return Ok(Header::Incomplete(V1_MAX_LENGTH)),
+    };
+
+    let line = std::str::from_utf8(&buf[..end])
+        .ok()
+        .and_then(|line| line.strip_suffix("\r\n"))
+        .ok_or_else(|| invalid("v1协议头格式错误"))?;
+    let parts: Vec<&str> = line.split(' ').collect();
+
+    match parts.get(1) {
+        Some(&"UNKNOWN") => Ok(Header::Complete(end, None)),
+        Some(&"TCP4") | Some(&"TCP6") if parts.len() == 6 => {
+            let ip = parts[2].parse::<IpAddr>().map_err(|_| invalid("v1源地址无效"))?;
+            let port = parts[4].parse::<u16>().map_err(|_| invalid("v1源端口无效"))?;
+            Ok(Header::Complete(end, Some(SocketAddr::new(ip, port))))
+        }
+        _ => Err(invalid("v1协议头格式错误")),
+    }
+}
+
+// 解析v2二进制协议头
+fn parse_v2(buf: &[u8]) -> io::Result<Header> {
+    if buf.len() < 16 {
+        return Ok(Header::Incomplete(16));
+    }
+    if buf[12] >> 4 != 2 {
+        return Err(invalid("不支持的v2版本"));
+    }
+
+    let end = 16 + u16::from_be_bytes([buf[14], buf[15]]) as usize;
+    if buf.len() < end {
+        return Ok(Header::Incomplete(end));
+    }
+    let payload = &buf[16..end];
+
+    // LOCAL命令表示代理自身的连接（如健康检查）
+    match buf[12] & 0x0F {
+        0 => return Ok(Header::Complete(end, None)),
+        1 => {}
+        _ => return Err(invalid("不支持的v2命令")),
+    }
+
+    match buf[13] >> 4 {
+        // AF_INET: 源地址4字节 + 目标地址4字节 + 源端口 + 目标端口
+        1 if payload.len() >= 12 => {
+            let ip = Ipv4Addr::new(payload[0], payload[1], payload[2], payload[3]);
+            let port = u16::from_be_bytes([payload[8], payload[9]]);
+            Ok(Header::Complete(end, Some(SocketAddr::new(IpAddr::V4(ip), port))))
+        }
+        // AF_INET6: 源地址16字节 + 目标地址16字节 + 源端口 + 目标端口
+        2 if payload.len() >= 36 => {
+            let mut octets = [0u8; 16];
+            octets.copy_from_slice(&payload[..16]);
+            let port = u16::from_be_bytes([payload[32], payload[33]]);
+            Ok(Header::Complete(end, Some(SocketAddr::new(IpAddr::V6(Ipv6Addr::from(octets)), port))))
+        }
+        1 | 2 => Err(invalid("v2地址长度不足")),
+        // AF_UNSPEC / AF_UNIX 没有可用的IP地址
+        _ => Ok(Header::Complete(end, None)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncWriteExt;
+
+    // 构造v2协议头
+    fn v2(command: u8, family: u8, payload: &[u8]) -> Vec<u8> {
+        let mut header = V2_SIGNATURE.to_vec();
+        header.push(0x20 | command);
+        header.push(family << 4 | 1);
+        header.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+        header.extend_from_slice(payload);
+        header
+    }
+
+    fn complete(buf: &[u8]) -> (usize, Option<SocketAddr>) {
+        match parse_header(buf).unwrap() {
+            Header::Complete(length, source) => (length, source),
+            header => panic!("协议头不完整: {:?}", header),
+        }
+    }
+
+    #[test]
+    fn parses_v1_headers() {
+        let tcp4 = b"PROXY TCP4 192.0.2.1 192.0.2.2 56324 443\r\nGET / HTTP/1.1\r\n";
+        assert_eq!(complete(tcp4), (42, Some("192.0.2.1:56324".parse().unwrap())));
+
+        let tcp6 = b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n";
+        assert_eq!(complete(tcp6), (tcp6.len(), Some("[2001:db8::1]:56324".parse().unwrap())));
+
+        assert_eq!(complete(b"PROXY UNKNOWN\r\n"), (15, None));
+    }
+
+    #[test]
+    fn parses_v2_headers() {
+        let header = v2(0, 0, &[]);
+        assert_eq!(complete(&header), (16, None));
+
+        let mut payload = vec![192, 0, 2, 1, 192, 0, 2, 2];
+        payload.extend_from_slice(&56324u16.to_be_bytes());
+        payload.extend_from_slice(&443u16.to_be_bytes());
+        let mut header = v2(1, 1, &payload);
+        header.extend_from_slice(b"GET");
+        assert_eq!(complete(&header), (28, Some("192.0.2.1:56324".parse().unwrap())));
+
+        let mut payload = "2001:db8::1".parse::<Ipv6Addr>().unwrap().octets().to_vec();
+        payload.extend_from_slice(&"2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        payload.extend_from_slice(&56324u16.to_be_bytes());
+        payload.extend_from_slice(&443u16.to_be_bytes());
+        let header = v2(1, 2, &payload);
+        assert_eq!(complete(&header), (52, Some("[2001:db8::1]:56324".parse().unwrap())));
+    }
+
+    #[test]
+    fn waits_for_truncated_headers() {
+        assert_eq!(parse_header(b"PRO").unwrap(), Header::Incomplete(V1_MAX_LENGTH));
+        assert_eq!(parse_header(b"PROXY TCP4 192.0.2.1").unwrap(), Header::Incomplete(V1_MAX_LENGTH));
+        assert_eq!(parse_header(&V2_SIGNATURE[..8]).unwrap(), Header::Incomplete(16));
+
+        let header = v2(1, 1, &[0; 12]);
+        assert_eq!(parse_header(&header[..20]).unwrap(), Header::Incomplete(28));
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        assert!(parse_header(b"GET / HTTP/1.1\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 192.0.2.1 192.0.2.2 56324\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 not-an-ip 192.0.2.2 56324 443\r\n").is_err());
+        assert!(parse_header(b"PROXY TCP4 192.0.2.1 192.0.2.2 99999 443\r\n").is_err());
+        assert!(parse_header(b"PROXY UNKNOWN\n").is_err());
+        assert!(parse_header(&[b"PROXY ".as_slice(), &[b'x'; V1_MAX_LENGTH]].concat()).is_err());
+
+        // 版本错误、未知命令、地址长度不足
+        let mut header = v2(1, 1, &[0; 12]);
+        header[12] = 0x11;
+        assert!(parse_header(&header).is_err());
+        assert!(parse_header(&v2(2, 1, &[0; 12])).is_err());
+        assert!(parse_header(&v2(1, 1, &[0; 8])).is_err());
+        assert!(parse_header(&v2(1, 2, &[0; 12])).is_err());
+    }
+
+    #[tokio::test]
+    async fn reads_only_the_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        let (mut stream, _) = listener.accept().await.unwrap();
+
+        // 分两次发送，协议头跨越多个数据包
+        client.write_all(b"PROXY TCP4 192.0.2.1 ").await.unwrap();
+        let writer = tokio::spawn(async move {
+            sleep(Duration::from_millis(50)).await;
+            client.write_all(b"192.0.2.2 56324 443\r\nGET").await.unwrap();
+            client
+        });
+
+        let source = read_header(&mut stream).await.unwrap();
+        assert_eq!(source, Some("192.0.2.1:56324".parse().unwrap()));
+
+        let _client = writer.await.unwrap();
+        let mut rest = [0u8; 3];
+        stream.read_exact(&mut rest).await.unwrap();
+        assert_eq!(&rest, b"GET");
+    }
+
+    // 连接监听器并发送伪造的协议头
+    async fn connect_with_forged_header(listener: &ProxyProtocolListener) -> TcpStream {
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).await.unwrap();
+        client.write_all(b"PROXY TCP4 127.0.0.1 127.0.0.1 56324 443\r\nGET / HTTP/1.1\r\n\r\n").await.unwrap();
+        client
+    }
+
+    #[tokio::test]
+    async fn rejects_header_from_untrusted_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut listener = ProxyProtocolListener::with_trust_check(listener, |_| false).unwrap();
+        let mut client = connect_with_forged_header(&listener).await;
+
+        // 连接被直接关闭，不会交给HTTP服务
+        assert!(timeout(Duration::from_millis(200), listener.accept()).await.is_err());
+        let mut buf = [0u8; 1];
+        let read = timeout(Duration::from_secs(1), client.read(&mut buf)).await.unwrap();
+        assert!(matches!(read, Ok(0) | Err(_)));
+    }
+
+    #[tokio::test]
+    async fn accepts_header_from_trusted_peer() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut listener = ProxyProtocolListener::with_trust_check(listener, |_| true).unwrap();
+        let _client = connect_with_forged_header(&listener).await;
+
+        let (_, source) = timeout(Duration::from_secs(1), listener.accept()).await.unwrap();
+        assert_eq!(source, "127.0.0.1:56324".parse().unwrap());
+    }
+}
@@ -18,7 +18,7 @@ pub async fn serve_static_file(Path(path): Path<String>) -> impl IntoResponse {
         let content = file.contents();
         
         // 根据文件扩展名设置 Content-Type
-        let content_type = match path.split('.').last() {
+        let content_type = match path.split('.').next_back() {
             Some("css") => "text/css",
             Some("js") => "application/javascript",
             Some("html") => "text/html",
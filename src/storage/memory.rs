@@ -0,0 +1,491 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::config::RetentionAction;
+use crate::db::{GeoGroup, ImportSummary, IpVisitExport, IpVisitRecord, PublicNote, UserChannelSetting, UserNote};
+use crate::geoip::GeoLocation;
+use super::{merge_geo, Storage, StorageResult};
+
+// 时间桶 -> 访客标识 -> 访问次数
+type VisitBuckets = BTreeMap<u64, HashMap<String, u64>>;
+
+// 地理分组键 (大洲, 国家, 城市)
+type GeoKey<'a> = (Option<&'a str>, Option<&'a str>, Option<&'a str>);
+
+#[derive(Default)]
+struct MemoryData {
+    total_visits: u64,
+    visits: HashMap<String, IpVisitExport>,
+    hour_buckets: VisitBuckets,
+    day_buckets: VisitBuckets,
+    // 匿名化标识的序号
+    anon_sequence: u64,
+    // 爬虫关键字 -> 访问次数
+    bot_visits: HashMap<String, u64>,
+    notes: HashMap<String, UserNote>,
+    public_notes: HashMap<u32, PublicNote>,
+    channel_settings: HashMap<String, UserChannelSetting>,
+}
+
+// 内存存储，不依赖数据库文件，重启后数据丢失
+pub struct MemoryStorage {
+    data: Mutex<MemoryData>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self {
+            data: Mutex::new(MemoryData::default()),
+        }
+    }
+
+    fn data(&self) -> StorageResult<MutexGuard<'_, MemoryData>> {
+        self.data.lock().map_err(|_| "无法获取内存存储锁".to_string())
+    }
+}
+
+// 获取当前时间戳
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+// 将地理位置写入访问记录
+fn apply_geo(record: &mut IpVisitExport, geo: &GeoLocation) {
+    let mut current = GeoLocation {
+        continent_code: record.continent_code.take(),
+        continent_name: record.continent_name.take(),
+        country_code: record.country_code.take(),
+        country_name: record.country_name.take(),
+        state_prov: record.state_prov.take(),
+        city: record.city.take(),
+        latitude: record.latitude,
+        longitude: record.longitude,
+    };
+    merge_geo(&mut current, geo);
+
+    record.continent_code = current.continent_code;
+    record.continent_name = current.continent_name;
+    record.country_code = current.country_code;
+    record.country_name = current.country_name;
+    record.state_prov = current.state_prov;
+    record.city = current.city;
+    record.latitude = current.latitude;
+    record.longitude = current.longitude;
+}
+
+// 已有的值优先，缺失时使用导入的值
+fn fill_missing<T: Clone>(field: &mut Option<T>, value: &Option<T>) {
+    if field.is_none() {
+        field.clone_from(value);
+    }
+}
+
+// 生成新的匿名标识
+fn next_anon_key(sequence: &mut u64) -> String {
+    *sequence += 1;
+    format!("anon:{}", sequence)
+}
+
+// 匿名化早于指定时间的时间桶中的访客标识
+fn anonymize_buckets(buckets: &mut VisitBuckets, before: u64, sequence: &mut u64) {
+    for visitors in buckets.range_mut(..before).map(|(_, visitors)| visitors) {
+        let keys: Vec<String> = visitors.keys().filter(|key| !key.starts_with("anon:")).cloned().collect();
+        for key in keys {
+            if let Some(visits) = visitors.remove(&key) {
+                visitors.insert(next_anon_key(sequence), visits);
+            }
+        }
+    }
+}
+
+// 匿名化所有时间桶中指定访客的标识
+fn anonymize_visitor(buckets: &mut VisitBuckets, key: &str, sequence: &mut u64) {
+    for visitors in buckets.values_mut() {
+        if let Some(visits) = visitors.remove(key) {
+            visitors.insert(next_anon_key(sequence), visits);
+        }
+    }
+}
+
+// 删除早于指定时间的时间桶，返回删除的访客行数
+fn purge_buckets(buckets: &mut VisitBuckets, before: u64) -> usize {
+    let kept = buckets.split_off(&before);
+    let removed = std::mem::replace(buckets, kept);
+    removed.values().map(|visitors| visitors.len()).sum()
+}
+
+// 平均值，忽略缺失的值
+fn average(values: impl Iterator<Item = Option<f64>>) -> Option<f64> {
+    let (sum, count) = values.flatten().fold((0.0, 0), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+impl Storage for MemoryStorage {
+    fn record_visit(&self, key: &str, geo: GeoLocation, timestamp: u64) {
+        let mut data = match self.data() {
+            Ok(data) => data,
+            Err(_) => return,
+        };
+        data.total_visits += 1;
+
+        let record = data.visits.entry(key.to_string()).or_insert_with(|| IpVisitExport {
+            ip: key.to_string(),
+            visit_count: 0,
+            continent_code: None,
+            continent_name: None,
+            country_code: None,
+            country_name: None,
+            state_prov: None,
+            city: None,
+            last_visit: None,
+            latitude: None,
+            longitude: None,
+        });
+        record.visit_count += 1;
+        record.last_visit = Some(timestamp);
+        apply_geo(record, &geo);
+
+        *data.hour_buckets.entry(timestamp - timestamp % 3600).or_default().entry(key.to_string()).or_insert(0) += 1;
+        *data.day_buckets.entry(timestamp - timestamp % 86400).or_default().entry(key.to_string()).or_insert(0) += 1;
+    }
+
+    // 访问直接写入内存，没有需要写入的数据
+    fn flush_visits(&self) -> StorageResult<(u64, usize)> {
+        Ok((0, 0))
+    }
+
+    fn total_visits(&self) -> StorageResult<u64> {
+        Ok(self.data()?.total_visits)
+    }
+
+    fn unique_visitors(&self) -> StorageResult<usize> {
+        Ok(self.data()?.visits.len())
+    }
+
+    fn visitor_visits(&self, key: &str) -> StorageResult<u64> {
+        Ok(self.data()?.visits.get(key).map(|record| record.visit_count).unwrap_or(0))
+    }
+
+    fn visit_detail(&self, key: &str) -> StorageResult<Option<IpVisitRecord>> {
+        Ok(self.data()?.visits.get(key).map(|record| IpVisitRecord {
+            ip: record.ip.clone(),
+            visit_count: record.visit_count,
+            continent_code: record.continent_code.clone(),
+            continent_name: record.continent_name.clone(),
+            country_code: record.country_code.clone(),
+            country_name: record.country_name.clone(),
+            state_prov: record.state_prov.clone(),
+            city: record.city.clone(),
+            last_visit: record.last_visit.unwrap_or(0),
+        }))
+    }
+
+    fn update_visit_geo(&self, key: &str, geo: &GeoLocation) -> StorageResult<bool> {
+        match self.data()?.visits.get_mut(key) {
+            Some(record) => {
+                apply_geo(record, geo);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    // 时间序列中的计数保留，标识匿名化
+    fn delete_visitor(&self, key: &str) -> StorageResult<bool> {
+        let mut data = self.data()?;
+        let deleted = data.visits.remove(key).is_some();
+
+        let MemoryData { hour_buckets, day_buckets, anon_sequence, .. } = &mut *data;
+        anonymize_visitor(hour_buckets, key, anon_sequence);
+        anonymize_visitor(day_buckets, key, anon_sequence);
+
+        Ok(deleted)
+    }
+
+    fn visit_history(&self, granularity: &str, since: u64) -> StorageResult<Vec<(u64, u64, u64)>> {
+        let data = self.data()?;
+        let buckets = match granularity {
+            "hour" => &data.hour_buckets,
+            "day" => &data.day_buckets,
+            _ => return Ok(Vec::new()),
+        };
+
+        Ok(buckets.range(since..).map(|(bucket, visitors)| {
+            (*bucket, visitors.values().sum(), visitors.len() as u64)
+        }).collect())
+    }
+
+    fn geo_breakdown(&self, level: &str, min_count: u64) -> StorageResult<(Vec<GeoGroup>, u64, u64)> {
+        if !["continent", "country", "city"].contains(&level) {
+            return Ok((Vec::new(), 0, 0));
+        }
+
+        let data = self.data()?;
+        let mut grouped: HashMap<GeoKey, Vec<&IpVisitExport>> = HashMap::new();
+        for record in data.visits.values() {
+            let country = if level == "continent" { None } else { record.country_code.as_deref() };
+            let city = if level == "city" { record.city.as_deref() } else { None };
+            grouped.entry((record.continent_code.as_deref(), country, city)).or_default().push(record);
+        }
+
+        let mut groups: Vec<GeoGroup> = grouped.into_values().map(|records| {
+            let first = records[0];
+            GeoGroup {
+                continent_code: first.continent_code.clone(),
+                continent_name: first.continent_name.clone(),
+                country_code: if level == "continent" { None } else { first.country_code.clone() },
+                country_name: if level == "continent" { None } else { first.country_name.clone() },
+                city: if level == "city" { first.city.clone() } else { None },
+                visits: records.iter().map(|record| record.visit_count).sum(),
+                unique_ips: records.len() as u64,
+                latitude: average(records.iter().map(|record| record.latitude)),
+                longitude: average(records.iter().map(|record| record.longitude)),
+            }
+        }).collect();
+        groups.sort_by_key(|group| std::cmp::Reverse(group.visits));
+
+        // 未知位置和人数过少的分组合并到其他
+        let (groups, other): (Vec<GeoGroup>, Vec<GeoGroup>) = groups.into_iter()
+            .partition(|group| group.unique_ips >= min_count && group.continent_code.is_some());
+        let other_visits = other.iter().map(|group| group.visits).sum();
+        let other_ips = other.iter().map(|group| group.unique_ips).sum();
+
+        Ok((groups, other_visits, other_ips))
+    }
+
+    fn export_visits(&self) -> StorageResult<(u64, Vec<IpVisitExport>)> {
+        let data = self.data()?;
+        let mut records: Vec<IpVisitExport> = data.visits.values().cloned().collect();
+        records.sort_by(|a, b| a.ip.cmp(&b.ip));
+        Ok((data.total_visits, records))
+    }
+
+    fn import_visits(&self, total_visits: u64, records: &[IpVisitExport]) -> StorageResult<ImportSummary> {
+        let mut data = self.data()?;
+        let mut summary = ImportSummary {
+            total_visits_added: total_visits,
+            ..Default::default()
+        };
+        data.total_visits += total_visits;
+
+        for record in records {
+            match data.visits.get_mut(&record.ip) {
+                Some(existing) => {
+                    existing.visit_count += record.visit_count;
+                    existing.last_visit = Some(existing.last_visit.unwrap_or(0).max(record.last_visit.unwrap_or(0)));
+                    fill_missing(&mut existing.continent_code, &record.continent_code);
+                    fill_missing(&mut existing.continent_name, &record.continent_name);
+                    fill_missing(&mut existing.country_code, &record.country_code);
+                    fill_missing(&mut existing.country_name, &record.country_name);
+                    fill_missing(&mut existing.state_prov, &record.state_prov);
+                    fill_missing(&mut existing.city, &record.city);
+                    fill_missing(&mut existing.latitude, &record.latitude);
+                    fill_missing(&mut existing.longitude, &record.longitude);
+                    summary.merged += 1;
+                }
+                None => {
+                    data.visits.insert(record.ip.clone(), record.clone());
+                    summary.inserted += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    fn expire_visitors(&self, before: u64, action: RetentionAction) -> StorageResult<usize> {
+        let mut data = self.data()?;
+        let expired: Vec<String> = data.visits.values()
+            .filter(|record| record.last_visit.is_some_and(|last_visit| last_visit < before))
+            .filter(|record| action == RetentionAction::Delete || !record.ip.starts_with("anon:"))
+            .map(|record| record.ip.clone())
+            .collect();
+
+        for key in expired.iter() {
+            let Some(mut record) = data.visits.remove(key) else { continue };
+            if action == RetentionAction::Anonymize {
+                record.ip = next_anon_key(&mut data.anon_sequence);
+                record.state_prov = None;
+                record.city = None;
+                record.latitude = None;
+                record.longitude = None;
+                data.visits.insert(record.ip.clone(), record);
+            }
+        }
+
+        // 时间序列只保留计数，标识一律匿名化
+        let MemoryData { hour_buckets, day_buckets, anon_sequence, .. } = &mut *data;
+        anonymize_buckets(hour_buckets, before, anon_sequence);
+        anonymize_buckets(day_buckets, before, anon_sequence);
+
+        Ok(expired.len())
+    }
+
+    fn purge_visit_history(&self, hour_before: u64, day_before: u64) -> StorageResult<usize> {
+        let mut data = self.data()?;
+        Ok(purge_buckets(&mut data.hour_buckets, hour_before) + purge_buckets(&mut data.day_buckets, day_before))
+    }
+
+    // 只计数，不保存最后访问时间
+    fn record_bot_visit(&self, pattern: &str, _timestamp: u64) -> StorageResult<()> {
+        *self.data()?.bot_visits.entry(pattern.to_string()).or_insert(0) += 1;
+        Ok(())
+    }
+
+    fn bot_visits(&self) -> StorageResult<u64> {
+        Ok(self.data()?.bot_visits.values().sum())
+    }
+
+    fn get_user_note(&self, user_id: &str) -> StorageResult<Option<UserNote>> {
+        Ok(self.data()?.notes.get(user_id).cloned())
+    }
+
+    fn save_user_note(&self, user_id: &str, content: &str) -> StorageResult<()> {
+        self.data()?.notes.insert(user_id.to_string(), UserNote {
+            user_id: user_id.to_string(),
+            content: content.to_string(),
+            last_updated: now_secs(),
+        });
+        Ok(())
+    }
+
+    fn get_public_note(&self, channel_id: u32) -> StorageResult<Option<PublicNote>> {
+        Ok(self.data()?.public_notes.get(&channel_id).cloned())
+    }
+
+    fn save_public_note(&self, channel_id: u32, content: &str) -> StorageResult<()> {
+        self.data()?.public_notes.insert(channel_id, PublicNote {
+            channel_id,
+            content: content.to_string(),
+            last_updated: now_secs(),
+        });
+        Ok(())
+    }
+
+    fn get_channel_setting(&self, user_id: &str) -> StorageResult<Option<UserChannelSetting>> {
+        Ok(self.data()?.channel_settings.get(user_id).cloned())
+    }
+
+    fn save_channel_setting(&self, user_id: &str, channel_id: u32) -> StorageResult<()> {
+        self.data()?.channel_settings.insert(user_id.to_string(), UserChannelSetting {
+            user_id: user_id.to_string(),
+            channel_id,
+            last_updated: now_secs(),
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn geo(continent: &str, country: &str, city: &str, latitude: f64) -> GeoLocation {
+        GeoLocation {
+            continent_code: Some(continent.to_string()),
+            continent_name: Some(continent.to_string()),
+            country_code: Some(country.to_string()),
+            country_name: Some(country.to_string()),
+            state_prov: None,
+            city: Some(city.to_string()),
+            latitude: Some(latitude),
+            longitude: Some(0.0),
+        }
+    }
+
+    #[test]
+    fn records_visit_history() {
+        let storage = MemoryStorage::new();
+        storage.record_visit("a", GeoLocation::default(), 86400 + 10);
+        storage.record_visit("a", GeoLocation::default(), 86400 + 20);
+        storage.record_visit("b", GeoLocation::default(), 86400 + 3600);
+        storage.record_visit("a", GeoLocation::default(), 2 * 86400);
+
+        assert_eq!(storage.visit_history("hour", 0).unwrap(), vec![
+            (86400, 2, 1),
+            (86400 + 3600, 1, 1),
+            (2 * 86400, 1, 1),
+        ]);
+        assert_eq!(storage.visit_history("day", 86400).unwrap(), vec![(86400, 3, 2), (2 * 86400, 1, 1)]);
+        assert_eq!(storage.visit_history("day", 86400 + 1).unwrap(), vec![(2 * 86400, 1, 1)]);
+        assert!(storage.visit_history("week", 0).unwrap().is_empty());
+
+        assert_eq!(storage.purge_visit_history(86400 + 3600, 2 * 86400).unwrap(), 3);
+        assert_eq!(storage.visit_history("hour", 0).unwrap().len(), 2);
+        assert_eq!(storage.visit_history("day", 0).unwrap(), vec![(2 * 86400, 1, 1)]);
+    }
+
+    #[test]
+    fn groups_visitors_by_location() {
+        let storage = MemoryStorage::new();
+        storage.record_visit("a", geo("EU", "FR", "Paris", 48.0), 100);
+        storage.record_visit("a", geo("EU", "FR", "Paris", 48.0), 100);
+        storage.record_visit("b", geo("EU", "FR", "Lyon", 46.0), 100);
+        storage.record_visit("c", geo("EU", "DE", "Berlin", 52.0), 100);
+        storage.record_visit("d", GeoLocation::default(), 100);
+
+        let (groups, other_visits, other_ips) = storage.geo_breakdown("country", 2).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].country_code.as_deref(), Some("FR"));
+        assert!(groups[0].city.is_none());
+        assert_eq!((groups[0].visits, groups[0].unique_ips, groups[0].latitude), (3, 2, Some(47.0)));
+        assert_eq!((other_visits, other_ips), (2, 2));
+
+        let (groups, other_visits, _) = storage.geo_breakdown("continent", 1).unwrap();
+        assert_eq!(groups.len(), 1);
+        assert!(groups[0].country_code.is_none());
+        assert_eq!((groups[0].visits, other_visits), (4, 1));
+
+        let (groups, _, _) = storage.geo_breakdown("city", 1).unwrap();
+        assert_eq!(groups.iter().map(|group| group.visits).collect::<Vec<_>>(), vec![2, 1, 1]);
+    }
+
+    #[test]
+    fn expires_visitors() {
+        let storage = MemoryStorage::new();
+        storage.record_visit("old", geo("EU", "FR", "Paris", 48.0), 3600);
+        storage.record_visit("new", GeoLocation::default(), 10 * 86400);
+
+        assert_eq!(storage.expire_visitors(86400, RetentionAction::Anonymize).unwrap(), 1);
+        assert_eq!(storage.visitor_visits("old").unwrap(), 0);
+        assert_eq!(storage.unique_visitors().unwrap(), 2);
+        let (_, records) = storage.export_visits().unwrap();
+        let anonymized = records.iter().find(|record| record.ip.starts_with("anon:")).unwrap();
+        assert_eq!(anonymized.country_code.as_deref(), Some("FR"));
+        assert!(anonymized.city.is_none() && anonymized.latitude.is_none());
+
+        // 时间序列保留计数，标识已匿名化
+        assert_eq!(storage.visit_history("hour", 0).unwrap()[0], (3600, 1, 1));
+        assert_eq!(storage.expire_visitors(86400, RetentionAction::Anonymize).unwrap(), 0);
+
+        assert_eq!(storage.expire_visitors(86400, RetentionAction::Delete).unwrap(), 1);
+        assert_eq!(storage.unique_visitors().unwrap(), 1);
+        assert_eq!(storage.visitor_visits("new").unwrap(), 1);
+    }
+
+    #[test]
+    fn imports_and_exports_visits() {
+        let storage = MemoryStorage::new();
+        storage.record_visit("a", GeoLocation::default(), 100);
+
+        let (total_visits, mut records) = storage.export_visits().unwrap();
+        assert_eq!((total_visits, records.len()), (1, 1));
+
+        records[0].visit_count = 5;
+        records[0].last_visit = Some(200);
+        records[0].city = Some("Paris".to_string());
+        let mut imported = records[0].clone();
+        imported.ip = "b".to_string();
+        records.push(imported);
+
+        let summary = storage.import_visits(6, &records).unwrap();
+        assert_eq!((summary.inserted, summary.merged, summary.total_visits_added), (1, 1, 6));
+        assert_eq!(storage.total_visits().unwrap(), 7);
+        assert_eq!(storage.visitor_visits("a").unwrap(), 6);
+        let detail = storage.visit_detail("a").unwrap().unwrap();
+        assert_eq!((detail.last_visit, detail.city.as_deref()), (200, Some("Paris")));
+    }
+}
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use rimplog::info;
+use crate::config::{get_config, RetentionAction, StorageBackend};
+use crate::db::{GeoGroup, ImportSummary, IpVisitExport, IpVisitRecord, PublicNote, UserChannelSetting, UserNote};
+use crate::geoip::GeoLocation;
+
+mod memory;
+mod sqlite;
+
+pub use memory::MemoryStorage;
+pub use sqlite::SqliteStorage;
+
+// 存储操作结果，错误为可直接返回给调用方的描述
+pub type StorageResult<T> = Result<T, String>;
+
+// 处理器通过路由状态共享的存储实例
+pub type SharedStorage = Arc<dyn Storage>;
+
+// 访问统计、用户备忘录、公共便利贴和频道设置的存储接口
+// 访客标识为按隐私设置转换后的IP
+pub trait Storage: Send + Sync {
+    // 记录一次访问，实现可以先缓存在内存中
+    fn record_visit(&self, key: &str, geo: GeoLocation, timestamp: u64);
+
+    // 写入缓存的访问计数，返回写入的 (访问次数, 访客数)
+    fn flush_visits(&self) -> StorageResult<(u64, usize)>;
+
+    // 总访问次数
+    fn total_visits(&self) -> StorageResult<u64>;
+
+    // 独立访客数
+    fn unique_visitors(&self) -> StorageResult<usize>;
+
+    // 单个访客的访问次数，没有记录时为0
+    fn visitor_visits(&self, key: &str) -> StorageResult<u64>;
+
+    // 单个访客的访问记录
+    fn visit_detail(&self, key: &str) -> StorageResult<Option<IpVisitRecord>>;
+
+    // 补充访客的地理位置，未提供的字段保留原值，没有记录时返回false
+    fn update_visit_geo(&self, key: &str, geo: &GeoLocation) -> StorageResult<bool>;
+
+    // 删除访客记录，没有记录时返回false
+    fn delete_visitor(&self, key: &str) -> StorageResult<bool>;
+
+    // 访问时间序列 (时间桶, 访问次数, 独立访客数)，粒度为 hour 或 day
+    fn visit_history(&self, granularity: &str, since: u64) -> StorageResult<Vec<(u64, u64, u64)>>;
+
+    // 按地理层级聚合访客记录，只列出独立访客数不少于阈值的分组
+    // 返回 (分组, 低于阈值而未列出的访问次数和独立访客数)
+    fn geo_breakdown(&self, level: &str, min_count: u64) -> StorageResult<(Vec<GeoGroup>, u64, u64)>;
+
+    // 导出总访问次数和全部访客记录
+    fn export_visits(&self) -> StorageResult<(u64, Vec<IpVisitExport>)>;
+
+    // 合并导入的访客数据：相同访客的访问次数相加，最后访问时间取较晚者，已有的地理位置优先保留
+    fn import_visits(&self, total_visits: u64, records: &[IpVisitExport]) -> StorageResult<ImportSummary>;
+
+    // 按保留策略处理最后访问早于指定时间的访客记录，并匿名化该时间之前的时间序列标识
+    // 返回处理的访客记录数
+    fn expire_visitors(&self, before: u64, action: RetentionAction) -> StorageResult<usize>;
+
+    // 清理早于指定时间的小时和天时间序列
+    fn purge_visit_history(&self, hour_before: u64, day_before: u64) -> StorageResult<usize>;
+
+    // 记录一次爬虫访问，按匹配的User-Agent关键字计数
+    fn record_bot_visit(&self, pattern: &str, timestamp: u64) -> StorageResult<()>;
+
+    // 爬虫访问总次数
+    fn bot_visits(&self) -> StorageResult<u64>;
+
+    fn get_user_note(&self, user_id: &str) -> StorageResult<Option<UserNote>>;
+
+    fn save_user_note(&self, user_id: &str, content: &str) -> StorageResult<()>;
+
+    fn get_public_note(&self, channel_id: u32) -> StorageResult<Option<PublicNote>>;
+
+    fn save_public_note(&self, channel_id: u32, content: &str) -> StorageResult<()>;
+
+    fn get_channel_setting(&self, user_id: &str) -> StorageResult<Option<UserChannelSetting>>;
+
+    fn save_channel_setting(&self, user_id: &str, channel_id: u32) -> StorageResult<()>;
+}
+
+// 按配置创建存储实例
+pub fn create_storage() -> SharedStorage {
+    match get_config().database.storage {
+        StorageBackend::Sqlite => Arc::new(SqliteStorage::new()),
+        StorageBackend::Memory => {
+            info!("使用内存存储，访问统计和备忘录在重启后丢失");
+            Arc::new(MemoryStorage::new())
+        }
+    }
+}
+
+// 用新值覆盖地理位置中提供了的字段
+fn merge_geo(target: &mut GeoLocation, geo: &GeoLocation) {
+    for (field, value) in [
+        (&mut target.continent_code, &geo.continent_code),
+        (&mut target.continent_name, &geo.continent_name),
+        (&mut target.country_code, &geo.country_code),
+        (&mut target.country_name, &geo.country_name),
+        (&mut target.state_prov, &geo.state_prov),
+        (&mut target.city, &geo.city),
+    ] {
+        if value.is_some() {
+            field.clone_from(value);
+        }
+    }
+    target.latitude = geo.latitude.or(target.latitude);
+    target.longitude = geo.longitude.or(target.longitude);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::init_test_db;
+
+    // 两种存储共用的爬虫计数检查
+    fn check_bot_visits(storage: &dyn Storage) {
+        let before = storage.bot_visits().unwrap();
+        storage.record_bot_visit("storage-test-bot", 100).unwrap();
+        storage.record_bot_visit("storage-test-bot", 200).unwrap();
+        storage.record_bot_visit("storage-test-crawler", 200).unwrap();
+        assert_eq!(storage.bot_visits().unwrap(), before + 3);
+    }
+
+    // 删除访客后时间序列的计数保留，标识被匿名化，再次访问算作新的访客
+    fn check_delete_anonymizes_history(storage: &dyn Storage, key: &str) {
+        let timestamp = 123 * 3600;
+        storage.record_visit(key, GeoLocation::default(), timestamp);
+        storage.flush_visits().unwrap();
+        assert!(storage.delete_visitor(key).unwrap());
+        assert!(!storage.delete_visitor(key).unwrap());
+        assert_eq!(storage.visitor_visits(key).unwrap(), 0);
+
+        storage.record_visit(key, GeoLocation::default(), timestamp + 10);
+        storage.flush_visits().unwrap();
+        let history = storage.visit_history("hour", timestamp).unwrap();
+        assert_eq!(history.first(), Some(&(timestamp, 2, 2)));
+    }
+
+    #[test]
+    fn delete_anonymizes_history_in_memory() {
+        check_delete_anonymizes_history(&MemoryStorage::new(), "storage-test-delete");
+    }
+
+    #[test]
+    fn delete_anonymizes_history_in_sqlite() {
+        init_test_db();
+        check_delete_anonymizes_history(&SqliteStorage::new(), "storage-test-delete");
+    }
+
+    #[test]
+    fn counts_bot_visits_in_memory() {
+        check_bot_visits(&MemoryStorage::new());
+    }
+
+    #[test]
+    fn counts_bot_visits_in_sqlite() {
+        init_test_db();
+        check_bot_visits(&SqliteStorage::new());
+    }
+}
@@ -0,0 +1,201 @@
+use std::sync::Mutex;
+use crate::config::RetentionAction;
+use crate::db::{self, GeoGroup, ImportSummary, IpVisitExport, IpVisitRecord, PendingIpVisit, PublicNote, UserChannelSetting, UserNote, VisitBatch};
+use crate::geoip::GeoLocation;
+use super::{merge_geo, Storage, StorageResult};
+
+// SQLite存储，访问计数先累计在内存中，由定时任务和关闭时批量写入
+pub struct SqliteStorage {
+    pending: Mutex<VisitBatch>,
+}
+
+impl SqliteStorage {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(VisitBatch::default()),
+        }
+    }
+
+    fn pending(&self) -> StorageResult<std::sync::MutexGuard<'_, VisitBatch>> {
+        self.pending.lock().map_err(|_| "无法获取访问计数锁".to_string())
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn record_visit(&self, key: &str, geo: GeoLocation, timestamp: u64) {
+        let mut pending = match self.pending() {
+            Ok(pending) => pending,
+            Err(_) => return,
+        };
+        pending.total_visits += 1;
+        *pending.buckets.entry((timestamp - timestamp % 3600, key.to_string())).or_insert(0) += 1;
+
+        let visit = pending.ips.entry(key.to_string()).or_insert_with(|| PendingIpVisit {
+            geo,
+            ..Default::default()
+        });
+        visit.visits += 1;
+        visit.last_visit = timestamp;
+    }
+
+    // 在一个事务中写入，失败时放回内存等待下次写入
+    fn flush_visits(&self) -> StorageResult<(u64, usize)> {
+        let batch = std::mem::take(&mut *self.pending()?);
+        if batch.is_empty() {
+            return Ok((0, 0));
+        }
+
+        let written = (batch.total_visits, batch.ips.len());
+        if let Err(e) = db::flush_visits(&batch) {
+            self.pending()?.merge(batch);
+            return Err(e.to_string());
+        }
+
+        Ok(written)
+    }
+
+    fn total_visits(&self) -> StorageResult<u64> {
+        let pending = self.pending()?.total_visits;
+        Ok(db::get_total_visits().map_err(|e| e.to_string())? + pending)
+    }
+
+    // 包含尚未写入数据库的新访客
+    fn unique_visitors(&self) -> StorageResult<usize> {
+        let pending: Vec<String> = self.pending()?.ips.keys().cloned().collect();
+        let known = db::count_known_ips(&pending).map_err(|e| e.to_string())?;
+        Ok(db::get_unique_ip_count().map_err(|e| e.to_string())? + pending.len() - known)
+    }
+
+    fn visitor_visits(&self, key: &str) -> StorageResult<u64> {
+        let pending = self.pending()?.ips.get(key).map(|visit| visit.visits).unwrap_or(0);
+        Ok(db::get_ip_visit_count(key).map_err(|e| e.to_string())? + pending)
+    }
+
+    // 先写入缓存的访问计数，返回完整的记录
+    fn visit_detail(&self, key: &str) -> StorageResult<Option<IpVisitRecord>> {
+        self.flush_visits()?;
+        db::get_ip_visit_detail(key).map_err(|e| e.to_string())
+    }
+
+    // 访问记录可能还未写入数据库，同时更新待写入的地理位置
+    fn update_visit_geo(&self, key: &str, geo: &GeoLocation) -> StorageResult<bool> {
+        let pending = match self.pending()?.ips.get_mut(key) {
+            Some(visit) => {
+                merge_geo(&mut visit.geo, geo);
+                true
+            }
+            None => false,
+        };
+
+        let updated = db::update_ip_geo(
+            key,
+            geo.continent_code.as_deref(),
+            geo.continent_name.as_deref(),
+            geo.country_code.as_deref(),
+            geo.country_name.as_deref(),
+            geo.state_prov.as_deref(),
+            geo.city.as_deref(),
+        ).map_err(|e| e.to_string())?;
+
+        Ok(updated || pending)
+    }
+
+    // 先写入缓存的访问计数，避免删除后再被写回
+    fn delete_visitor(&self, key: &str) -> StorageResult<bool> {
+        self.flush_visits()?;
+        db::delete_ip_visit(key).map_err(|e| e.to_string())
+    }
+
+    fn visit_history(&self, granularity: &str, since: u64) -> StorageResult<Vec<(u64, u64, u64)>> {
+        db::get_visit_history(granularity, since).map_err(|e| e.to_string())
+    }
+
+    fn geo_breakdown(&self, level: &str, min_count: u64) -> StorageResult<(Vec<GeoGroup>, u64, u64)> {
+        db::get_geo_breakdown(level, min_count).map_err(|e| e.to_string())
+    }
+
+    // 先写入缓存的访问计数，导出完整的数据
+    fn export_visits(&self) -> StorageResult<(u64, Vec<IpVisitExport>)> {
+        self.flush_visits()?;
+        let total_visits = db::get_total_visits().map_err(|e| format!("读取总访问次数失败: {}", e))?;
+        let records = db::export_ip_visits().map_err(|e| format!("读取访客记录失败: {}", e))?;
+        Ok((total_visits, records))
+    }
+
+    fn import_visits(&self, total_visits: u64, records: &[IpVisitExport]) -> StorageResult<ImportSummary> {
+        db::import_visitor_data(total_visits, records).map_err(|e| e.to_string())
+    }
+
+    fn expire_visitors(&self, before: u64, action: RetentionAction) -> StorageResult<usize> {
+        let count = match action {
+            RetentionAction::Delete => db::delete_ip_visits_before(before),
+            RetentionAction::Anonymize => db::anonymize_ip_visits_before(before),
+        }.map_err(|e| e.to_string())?;
+        db::anonymize_visit_buckets_before(before).map_err(|e| e.to_string())?;
+        Ok(count)
+    }
+
+    fn purge_visit_history(&self, hour_before: u64, day_before: u64) -> StorageResult<usize> {
+        db::purge_visit_buckets(hour_before, day_before).map_err(|e| e.to_string())
+    }
+
+    fn record_bot_visit(&self, pattern: &str, timestamp: u64) -> StorageResult<()> {
+        db::increment_bot_visit(pattern, timestamp).map_err(|e| e.to_string())
+    }
+
+    fn bot_visits(&self) -> StorageResult<u64> {
+        db::get_bot_visit_count().map_err(|e| e.to_string())
+    }
+
+    fn get_user_note(&self, user_id: &str) -> StorageResult<Option<UserNote>> {
+        db::get_user_note(user_id).map_err(|e| e.to_string())
+    }
+
+    fn save_user_note(&self, user_id: &str, content: &str) -> StorageResult<()> {
+        db::save_user_note(user_id, content).map_err(|e| e.to_string())
+    }
+
+    fn get_public_note(&self, channel_id: u32) -> StorageResult<Option<PublicNote>> {
+        db::get_public_note(channel_id).map_err(|e| e.to_string())
+    }
+
+    fn save_public_note(&self, channel_id: u32, content: &str) -> StorageResult<()> {
+        db::save_public_note(channel_id, content).map_err(|e| e.to_string())
+    }
+
+    fn get_channel_setting(&self, user_id: &str) -> StorageResult<Option<UserChannelSetting>> {
+        db::get_user_channel_setting(user_id).map_err(|e| e.to_string())
+    }
+
+    fn save_channel_setting(&self, user_id: &str, channel_id: u32) -> StorageResult<()> {
+        db::save_user_channel_setting(user_id, channel_id).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::test_support::init_test_db;
+
+    #[test]
+    fn counts_pending_visitors_once() {
+        init_test_db();
+        let storage = SqliteStorage::new();
+        let before = storage.unique_visitors().unwrap();
+
+        storage.record_visit("sqlite-test-a", GeoLocation::default(), 1000);
+        storage.flush_visits().unwrap();
+        assert_eq!(storage.unique_visitors().unwrap(), before + 1);
+
+        // 已写入的访客再次访问不算新访客
+        storage.record_visit("sqlite-test-a", GeoLocation::default(), 1010);
+        storage.record_visit("sqlite-test-b", GeoLocation::default(), 1010);
+        storage.record_visit("sqlite-test-b", GeoLocation::default(), 1020);
+        assert_eq!(storage.unique_visitors().unwrap(), before + 2);
+        assert_eq!(storage.visitor_visits("sqlite-test-a").unwrap(), 2);
+
+        storage.flush_visits().unwrap();
+        assert_eq!(storage.unique_visitors().unwrap(), before + 2);
+        assert_eq!(storage.visitor_visits("sqlite-test-b").unwrap(), 2);
+    }
+}